@@ -105,11 +105,184 @@ ${CODE_BLOCK}
 ## Scriptify
 
 - ${CODE}--scriptify${CODE} option allows you to generate a shell script to show the braille text.
+- ${CODE}--script-shell${CODE} option selects the shell of the script: ${CODE}bash${CODE}, ${CODE}sh${CODE} (POSIX sh such as dash and BusyBox), ${CODE}fish${CODE} or ${CODE}powershell${CODE}. Without the option, the file extension selects it (${CODE}.fish${CODE}, ${CODE}.ps1${CODE}, ${CODE}.py${CODE}, ${CODE}.js${CODE}), otherwise ${CODE}bash${CODE}. The same applies to the ${CODE}script${CODE} output format.
+- ${CODE}python${CODE} and ${CODE}node${CODE} generate standalone Python 3 and Node.js players, which embed the frames (zlib + base64) and keep each frame on schedule. They run on Windows and macOS without bash.
+- Video scripts clear the screen and redraw each frame from the top-left corner, keeping the display time of each frame (ex: variable frame delays of animated GIFs).
+- ${CODE}--script-delta${CODE} option stores only the changed cells of each video frame, with a full frame every ${CODE}--keyframe-interval${CODE} frames (default 30). It makes scripts of long or large videos smaller and faster to play.
+- ${CODE}--script-loop N${CODE} plays a video script N times (default 1, ${CODE}0${CODE} repeats until Ctrl-C). ${CODE}--script-hide-cursor${CODE} hides the cursor and ${CODE}--script-alt-screen${CODE} plays in the alternate screen; the script restores the terminal when it ends or is interrupted.
+- ${CODE}--script-compression${CODE} option selects the compression of the embedded text: ${CODE}gzip${CODE} (default), ${CODE}xz${CODE}, ${CODE}zstd${CODE} or ${CODE}none${CODE}. ${CODE}--script-compression-level${CODE} sets the level (gzip and xz: 0-9, zstd: 1-22). xz makes animation scripts much smaller. The script checks that the decompressor is installed and tells you which one to install otherwise.
 
 ${CODE_BLOCK}
 $ brailler samples/test.mp4 --contrast stretch --binarize fsdith --size 0x60 --scriptify play_mov.sh
 
 $ ./play_mov.sh
+
+# Python player for colleagues without bash
+$ brailler samples/test.mp4 --size 0x60 --scriptify play_mov.py
+
+$ python3 play_mov.py
+${CODE_BLOCK}
+
+## Stdin
+
+- Passing ${CODE}-${CODE} as the input reads the image or video from stdin.
+
+${CODE_BLOCK}
+\$ curl -s https://example.com/image.png | brailler - --size 60x0
+\$ convert input.jpg png:- | brailler - --binarize fsdith
+${CODE_BLOCK}
+
+## Output
+
+- ${CODE}-o/--output${CODE} option writes the result to a file instead of stdout.
+- The extension of the output path selects the format.
+  - ${CODE}txt${CODE}: Plain braille text
+  - ${CODE}html${CODE}: HTML page with the braille text
+  - ${CODE}svg${CODE}: SVG image with each dot drawn as a circle
+  - ${CODE}png${CODE}: PNG image with each dot drawn as a circle
+  - ${CODE}ans${CODE}: Braille text with ANSI escape sequences to clear the screen
+  - ${CODE}sh${CODE}: Bash script (same as ${CODE}--scriptify${CODE})
+- Videos support ${CODE}txt${CODE}, ${CODE}ans${CODE} and ${CODE}sh${CODE}.
+
+${CODE_BLOCK}
+\$ brailler samples/cube.png --size 60x0 --output cube.svg
+${CODE_BLOCK}
+
+## Batch
+
+- Multiple inputs, directories and globs (${CODE}*${CODE}, ${CODE}?${CODE} in file names) are converted in parallel with the same options.
+- ${CODE}--output-dir${CODE} option specifies the directory to write the results to.
+- ${CODE}--name-template${CODE} option specifies the output file name. ${CODE}{stem}${CODE} and ${CODE}{name}${CODE} are replaced with the input file name, and the extension selects the format (see Output).
+
+${CODE_BLOCK}
+\$ brailler icons/ 'logos/*.png' --size 30x0 --output-dir out --name-template '{stem}.txt'
+${CODE_BLOCK}
+
+## Montage
+
+- ${CODE}montage${CODE} subcommand renders several inputs or several option sets for one input and lays them out in a grid.
+- ${CODE}--variant${CODE} option specifies an option set that overrides the common options. It can be specified multiple times.
+- ${CODE}--columns${CODE} option specifies the number of tiles per row, and ${CODE}--captions${CODE} shows a caption under each tile.
+
+${CODE_BLOCK}
+\$ brailler montage samples/cube.png --size 50x0 --captions \
+    --variant "--binarize none" \
+    --variant "--binarize odith" \
+    --variant "--binarize fsdith" \
+    --variant "--binarize otsu"
+${CODE_BLOCK}
+
+## Interactive
+
+- ${CODE}-i/--interactive${CODE} option plays a video with keyboard controls. A status line below the video shows the position, duration, speed and fps.
+  - ${CODE}space${CODE}: Pause / resume
+  - ${CODE}←${CODE} / ${CODE}→${CODE}: Seek 5 seconds backward / forward
+  - ${CODE}+${CODE} / ${CODE}-${CODE}: Change the playback speed (0.25x - 4x)
+  - ${CODE}l${CODE}: Toggle looping
+  - ${CODE}q${CODE} / ${CODE}Ctrl-C${CODE}: Quit
+
+${CODE_BLOCK}
+\$ brailler samples/test.mp4 --binarize fsdith --size 0x60 --interactive
+${CODE_BLOCK}
+
+## Trim
+
+- ${CODE}--start${CODE} option specifies the start position of the video, and ${CODE}--duration${CODE} or ${CODE}--end${CODE} specifies the length or the end position. Times are given as seconds (${CODE}2.5${CODE}) or ${CODE}[HH:]MM:SS[.sss]${CODE} (${CODE}01:30${CODE}).
+- ${CODE}--speed${CODE} option changes the playback speed (${CODE}2${CODE} plays twice as fast, ${CODE}0.5${CODE} at half speed).
+- ${CODE}--fps${CODE} option specifies the output frame rate. Frames are sampled to match it, which also keeps scripts small.
+
+${CODE_BLOCK}
+# Turn a 2-second slice of a long recording into a script at 10 fps
+\$ brailler samples/test.mp4 --start 01:30 --duration 2 --fps 10 --size 0x40 --output clip.sh
+${CODE_BLOCK}
+
+## Animation
+
+- Animated GIF, APNG and WebP are played like videos, with the delay of each frame. They don't need the ${CODE}video${CODE} feature.
+- ${CODE}--output${CODE}, ${CODE}--scriptify${CODE}, ${CODE}--interactive${CODE} and the trim options (see Trim) work the same as for videos.
+- In batch mode and montage, the first frame is converted.
+
+${CODE_BLOCK}
+\$ brailler samples/anim.gif --size 60x0
+\$ brailler samples/anim.gif --size 60x0 --scriptify play_anim.sh
+${CODE_BLOCK}
+
+## Asciicast
+
+- ${CODE}--format${CODE} option specifies the output format instead of the extension of the output path (${CODE}text${CODE}, ${CODE}html${CODE}, ${CODE}svg${CODE}, ${CODE}png${CODE}, ${CODE}ansi${CODE}, ${CODE}script${CODE}, ${CODE}asciicast${CODE}).
+- ${CODE}asciicast${CODE} (or the ${CODE}cast${CODE} extension) writes an [asciinema](https://asciinema.org/) asciicast v2 file with the timestamp of each frame, which can be replayed with ${CODE}asciinema play${CODE} or embedded with the asciinema player.
+
+${CODE_BLOCK}
+\$ brailler samples/test.mp4 --size 0x40 --output test.cast
+\$ asciinema play test.cast
+${CODE_BLOCK}
+
+## GIF
+
+- ${CODE}gif${CODE} format (or the ${CODE}gif${CODE} extension) draws each dot as a circle (no font needed) and writes an animated GIF with the timing of each frame. It is useful to share animations where Unicode braille is not rendered consistently.
+- Images are written as a single frame GIF.
+
+${CODE_BLOCK}
+\$ brailler samples/test.mp4 --size 0x40 --fps 15 --output test.gif
+${CODE_BLOCK}
+
+## Stable dithering
+
+- ${CODE}--binarize stable${CODE} dithers with a fixed threshold mask, so the dot pattern of a still region doesn't change between frames.
+- For videos and animations, a dot only flips when the luminance under it changes clearly from the previous frame. This removes the shimmer of ${CODE}fsdith${CODE} in static regions.
+
+${CODE_BLOCK}
+\$ brailler samples/test.mp4 --size 0x60 --binarize stable
+${CODE_BLOCK}
+
+## Dump frames
+
+- ${CODE}--dump-frames${CODE} option saves the processed frames as PNG to the specified directory for debugging. Each frame is saved before (${CODE}frame-NNNNNN-pre.png${CODE}) and after (${CODE}frame-NNNNNN-post.png${CODE}) binarization.
+- ${CODE}--dump-every${CODE} option saves only every Nth frame.
+
+${CODE_BLOCK}
+\$ brailler samples/test.mp4 --size 0x60 --binarize fsdith --dump-frames frames --dump-every 30
+${CODE_BLOCK}
+
+## Exit status
+
+- Errors are printed to stderr and brailler exits with a non-zero status.
+  - ${CODE}64${CODE}: Invalid size or options
+  - ${CODE}65${CODE}: Undecodable input or unsupported format
+  - ${CODE}70${CODE}: Video decoding (ffmpeg) error
+  - ${CODE}74${CODE}: File or terminal I/O error
+  - ${CODE}1${CODE}: Some files failed in batch mode
+
+## Subtitles
+
+- ${CODE}--subtitles${CODE} option shows SRT or WebVTT subtitles below the frames of a video or animation. It works for playback, ${CODE}--scriptify${CODE} and ${CODE}--output${CODE}.
+- Subtitles follow ${CODE}--start${CODE} and ${CODE}--speed${CODE}. Inline tags such as ${CODE}<i>${CODE} are removed, and up to 3 lines are shown.
+
+${CODE_BLOCK}
+\$ brailler samples/test.mp4 --size 0x60 --subtitles samples/test.srt
+${CODE_BLOCK}
+
+## Thumbnails
+
+- ${CODE}thumbnails${CODE} subcommand detects scene changes in a video or animation and shows the middle frame of each scene in a grid, with the start time of the scene. It is useful to preview recordings on a server without a display.
+- A scene changes where the luminance histogram differs from the previous frame by more than ${CODE}--threshold${CODE} (0.0-1.0, default 0.3). Scenes shorter than ${CODE}--min-scene${CODE} are merged into the previous scene.
+- ${CODE}--size${CODE} specifies the size of each thumbnail (${CODE}0x0${CODE} makes thumbnails 16 rows high), and ${CODE}--fps${CODE} analyzes the input at a lower frame rate to speed it up.
+
+${CODE_BLOCK}
+\$ brailler thumbnails samples/test.mp4 --size 40x0 --columns 3 --fps 5
+${CODE_BLOCK}
+
+## Inspect
+
+- Generated scripts start with a metadata comment block: brailler version, source file name and SHA-256, the options used, size, frame count, fps, shell and compression.
+- ${CODE}inspect${CODE} subcommand prints the metadata of a script. ${CODE}--frames${CODE} prints the braille frames embedded in the script instead, separated by blank lines (delta frames are restored in full).
+
+${CODE_BLOCK}
+\$ brailler samples/anim.gif --size 60x0 --scriptify play_anim.sh
+
+\$ brailler inspect play_anim.sh
+
+\$ brailler inspect play_anim.sh --frames > frames.txt
 ${CODE_BLOCK}
 
 EOD
@@ -0,0 +1,166 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageFormat};
+use std::io::Cursor;
+use std::time::Duration;
+
+use crate::args::{BinarizeOption, FrameOptions};
+use crate::braille::convert_size;
+use crate::dump::FrameDumper;
+use crate::error::{BraillerError, Result};
+use crate::image_processing::{FrameBinarizer, process_image};
+use crate::player::{Frame, FrameSource};
+use crate::size::Size;
+use crate::timeline::{Sample, Sampler};
+
+/// 表示時間がこれ以下のフレームは、ブラウザと同様に DEFAULT_DELAY で表示する
+const MIN_DELAY: Duration = Duration::from_millis(10);
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+pub struct AnimationData {
+    pub frames: AnimationFrames,
+    pub size: Size,
+    pub ratio: f32,
+    pub fps: f32,
+}
+
+/// 二値化済みのアニメーションのフレーム列
+pub struct AnimationFrames {
+    frames: Vec<Frame>,
+    index: usize,
+    duration: Duration,
+}
+
+impl Iterator for AnimationFrames {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.get(self.index)?;
+        self.index += 1;
+        Some(Ok(Frame {
+            image: frame.image.clone(),
+            pts: frame.pts,
+        }))
+    }
+}
+
+impl FrameSource for AnimationFrames {
+    fn seek(&mut self, pts: Duration) -> Result<()> {
+        self.index = self.frames.partition_point(|frame| frame.pts < pts);
+        Ok(())
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        Some(self.duration)
+    }
+}
+
+/// アニメーション GIF / APNG / WebP のデコーダを作る。アニメーションでない場合は None。
+fn decode(data: &[u8]) -> Option<image::Frames<'_>> {
+    match image::guess_format(data).ok()? {
+        ImageFormat::Gif => Some(GifDecoder::new(Cursor::new(data)).ok()?.into_frames()),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(data)).ok()?;
+            if !decoder.is_apng().ok()? {
+                return None;
+            }
+            Some(decoder.apng().ok()?.into_frames())
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(data)).ok()?;
+            if !decoder.has_animation() {
+                return None;
+            }
+            Some(decoder.into_frames())
+        }
+        _ => None,
+    }
+}
+
+/// 2フレーム以上のアニメーション GIF / APNG / WebP かを判定する
+pub fn is_animated(data: &[u8]) -> bool {
+    decode(data).is_some_and(|frames| frames.take(2).count() == 2)
+}
+
+/// フレームの表示時間
+fn frame_delay(frame: &image::Frame) -> Duration {
+    let delay = Duration::from(frame.delay());
+    if delay <= MIN_DELAY {
+        DEFAULT_DELAY
+    } else {
+        delay
+    }
+}
+
+/// アニメーションをデコードし、各フレームを前処理・二値化したフレーム列を返す。
+/// 各フレームの pts は、それより前のフレームの表示時間の合計になる。
+/// 動画と同様に --start, --duration / --end, --speed, --fps を適用する。
+pub fn load_frames(data: &[u8], options: &FrameOptions) -> Result<AnimationData> {
+    let timeline = options.timeline;
+    let decoded = decode(data).ok_or_else(|| {
+        BraillerError::UnsupportedFormat("not an animated GIF, APNG or WebP".to_string())
+    })?;
+    let mut sampler = Sampler::new(timeline, Duration::ZERO);
+
+    let mut binarizer = FrameBinarizer::new(options.render.binarize);
+    let mut dumper = FrameDumper::new(&options.dump_frames, options.dump_every)?;
+    let mut frames = Vec::new();
+    let mut size = None;
+    let mut source_pts = Duration::ZERO;
+    let mut frame_count = 0;
+    for frame in decoded {
+        let frame = frame?;
+        let delay = frame_delay(&frame);
+        let img = DynamicImage::ImageRgba8(frame.into_buffer()).to_luma8();
+        // 出力サイズは最初のフレームに合わせる (各フレームはキャンバス全体に合成済み)
+        let (w, h) = img.dimensions();
+        let &mut (cols, rows, _) = size.get_or_insert_with(|| {
+            let (cols, rows) = convert_size(w, h, options.render.size.0, options.render.size.1);
+            (cols, rows, w as f32 / h as f32 * 2f32)
+        });
+        match sampler.sample(source_pts) {
+            Sample::Emit(pts) => {
+                let pre = process_image(
+                    &img,
+                    cols,
+                    rows,
+                    options.render.contrast,
+                    options.render.invert,
+                    BinarizeOption::None,
+                )?;
+                // 二値化は前フレームの結果を引き継ぐ場合があるため、フレーム順に行う
+                let image = binarizer.binarize(&pre);
+                if let Some(dumper) = &mut dumper {
+                    dumper.dump(&pre, &image)?;
+                }
+                frames.push(Frame { image, pts });
+            }
+            Sample::Skip => {}
+            Sample::End => break,
+        }
+        source_pts += delay;
+        frame_count += 1;
+    }
+
+    let (cols, rows, ratio) =
+        size.ok_or_else(|| BraillerError::Decode("animation has no frames".to_string()))?;
+    if frames.is_empty() {
+        return Err(BraillerError::InvalidArgument(
+            "no frames in the specified range".to_string(),
+        ));
+    }
+    let source_fps = frame_count as f32 / source_pts.as_secs_f32();
+    Ok(AnimationData {
+        frames: AnimationFrames {
+            frames,
+            index: 0,
+            duration: timeline
+                .output_duration(Some(source_pts))
+                .unwrap_or_default(),
+        },
+        size: Size(cols * 2, rows * 4),
+        ratio,
+        fps: timeline.output_fps(source_fps)?,
+    })
+}
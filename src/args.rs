@@ -1,7 +1,12 @@
+use crate::error::{BraillerError, Result};
+use crate::export::OutputFormat;
+use crate::scriptify::{ScriptCompression, ScriptShell};
 use crate::size::Size;
+use crate::timeline::Timeline;
+use crate::timestamp::Timestamp;
 use clap::Parser; // Modified import to include ArgGroup // Added import for Size
 
-use clap::ValueEnum;
+use clap::{ArgGroup, Subcommand, ValueEnum};
 
 #[derive(ValueEnum, PartialEq, Clone, Debug, Copy)]
 pub enum ContrastOption {
@@ -16,15 +21,14 @@ pub enum BinarizeOption {
     Odith,
     Fsdith,
     Otsu,
+    /// Dithering that keeps dots stable across video frames
+    Stable,
 }
 
-#[derive(Parser, Debug, Clone)]
-#[command(author, version, about)]
-pub struct Args {
-    /// Input image file path or video file path
-    #[arg(value_name = "INPUT")]
-    pub input: String,
-
+// 画像からBraille文字列を生成する際のオプション。
+// Args に flatten されるため、ドキュメントコメントにすると about を上書きしてしまう
+#[derive(clap::Args, Debug, Clone)]
+pub struct RenderOptions {
     /// Output size({width}x{height})
     #[arg(short, long, default_value = "0x0")]
     pub size: Size,
@@ -40,12 +44,257 @@ pub struct Args {
     /// Binarize option
     #[arg(long, default_value = "none")]
     pub binarize: BinarizeOption,
+}
+
+/// 動画・アニメーションのフレームを読み込む際のオプション
+#[derive(Debug, Clone)]
+pub struct FrameOptions {
+    pub render: RenderOptions,
+    pub timeline: Timeline,
+    /// 処理途中のフレームを保存するディレクトリ (空の場合は保存しない)
+    pub dump_frames: String,
+    pub dump_every: u32,
+}
+
+impl FrameOptions {
+    pub fn new(args: &Args) -> Result<Self> {
+        Ok(Self {
+            render: args.render.clone(),
+            timeline: Timeline::new(args)?,
+            dump_frames: args.dump_frames.clone(),
+            dump_every: args.dump_every,
+        })
+    }
+}
 
-    /// Generate a bash script that shows the braille text
+#[derive(Parser, Debug, Clone)]
+#[command(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    group(ArgGroup::new("destination").args(["output", "output_dir"]))
+)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Input image file path or video file path ("-" reads from stdin).
+    /// Multiple files, directories and globs convert images in batch mode
+    #[arg(value_name = "INPUT", required = true)]
+    pub input: Vec<String>,
+
+    #[command(flatten)]
+    pub render: RenderOptions,
+
+    /// Generate a shell script that shows the braille text
     #[arg(long, default_value = "")]
     pub scriptify: String,
 
+    /// Shell of the generated script (--scriptify or the script output format).
+    /// Defaults to the file extension (.fish, .ps1, .py, .js), otherwise bash
+    #[arg(long)]
+    pub script_shell: Option<ScriptShell>,
+
+    /// Store only the changed cells of each video frame in the script
+    #[arg(long)]
+    pub script_delta: bool,
+
+    /// Store every Nth frame in full with --script-delta
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..))]
+    pub keyframe_interval: u32,
+
+    /// Number of times the video script plays (0 repeats until Ctrl-C)
+    #[arg(long, default_value_t = 1)]
+    pub script_loop: u32,
+
+    /// Hide the cursor while the video script plays
+    #[arg(long)]
+    pub script_hide_cursor: bool,
+
+    /// Play the video script in the alternate screen and return to the original screen at the end
+    #[arg(long)]
+    pub script_alt_screen: bool,
+
+    /// Compression of the text embedded in the script
+    #[arg(long, default_value = "gzip")]
+    pub script_compression: ScriptCompression,
+
+    /// Compression level (gzip and xz: 0-9, default 6; zstd: 1-22, default 3)
+    #[arg(long)]
+    pub script_compression_level: Option<u32>,
+
+    /// Output file path. The extension selects the format
+    /// (txt, html, svg, png, ans, cast, gif, or sh, fish, ps1, py, js for a script)
+    #[arg(short, long, default_value = "")]
+    pub output: String,
+
+    /// Output format. Inferred from the output file extension if not specified.
+    /// Requires --output or --output-dir
+    #[arg(long, requires = "destination")]
+    pub format: Option<OutputFormat>,
+
+    /// Output directory for batch mode
+    #[arg(long, default_value = "", conflicts_with_all = ["output", "scriptify"])]
+    pub output_dir: String,
+
+    /// Output file name template for batch mode. {stem} and {name} are replaced
+    /// with the input file name, and the extension selects the format
+    #[arg(long, default_value = "{stem}.txt")]
+    pub name_template: String,
+
+    /// Start position of the video (SECONDS or [HH:]MM:SS[.sss])
+    #[arg(long, default_value = "0")]
+    pub start: Timestamp,
+
+    /// Length of the video segment to render from --start
+    #[arg(long, conflicts_with = "end")]
+    pub duration: Option<Timestamp>,
+
+    /// End position of the video segment to render
+    #[arg(long)]
+    pub end: Option<Timestamp>,
+
+    /// Playback speed of the video (2 plays twice as fast)
+    #[arg(long, default_value_t = 1.0)]
+    pub speed: f32,
+
+    /// Output frame rate of the video. Frames are sampled to match it
+    /// (0 keeps the source frame rate)
+    #[arg(long, default_value_t = 0.0)]
+    pub fps: f32,
+
+    /// SRT or WebVTT subtitle file to show below the frames of a video or animation
+    #[arg(long, default_value = "")]
+    pub subtitles: String,
+
+    /// Directory to save processed frames before and after binarization as PNG
+    /// (frame-NNNNNN-pre.png, frame-NNNNNN-post.png) for debugging
+    #[arg(long, default_value = "")]
+    pub dump_frames: String,
+
+    /// Save every Nth frame with --dump-frames
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub dump_every: u32,
+
+    /// Play video with keyboard controls (space: pause, left/right: seek 5s,
+    /// +/-: speed, l: loop, q: quit)
+    #[arg(short, long)]
+    pub interactive: bool,
+
     /// Verbose mode
     #[arg(short, long)]
     pub verbose: bool,
 }
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Render several inputs or option sets side by side in a grid
+    Montage(MontageArgs),
+    /// Detect scene changes in a video or animation and show one frame per scene
+    Thumbnails(ThumbnailsArgs),
+    /// Show the metadata of a script generated by --scriptify, or extract its braille frames
+    Inspect(InspectArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MontageArgs {
+    /// Input image file paths
+    #[arg(value_name = "INPUT", required = true)]
+    pub input: Vec<String>,
+
+    #[command(flatten)]
+    pub render: RenderOptions,
+
+    /// Option set rendered for each input, overriding the common options
+    /// (ex: --variant "--binarize fsdith"). Can be specified multiple times
+    #[arg(long = "variant", value_name = "OPTIONS", allow_hyphen_values = true)]
+    pub variants: Vec<String>,
+
+    /// Number of tiles per row (0 puts all tiles in one row)
+    #[arg(long, default_value_t = 0)]
+    pub columns: usize,
+
+    /// Number of spaces between tiles
+    #[arg(long, default_value_t = 1)]
+    pub gap: usize,
+
+    /// Show a caption under each tile
+    #[arg(long)]
+    pub captions: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ThumbnailsArgs {
+    /// Input video or animation file path ("-" reads from stdin)
+    #[arg(value_name = "INPUT")]
+    pub input: String,
+
+    // --size はサムネイル1つの大きさ (0x0 の場合は高さ thumbnails::DEFAULT_ROWS)
+    #[command(flatten)]
+    pub render: RenderOptions,
+
+    /// Histogram difference between consecutive frames (0.0-1.0) regarded as a scene change
+    #[arg(long, default_value_t = 0.3)]
+    pub threshold: f32,
+
+    /// Minimum length of a scene. Changes within it are merged into the scene
+    #[arg(long, default_value = "1")]
+    pub min_scene: Timestamp,
+
+    /// Frame rate to analyze the input at (0 analyzes every frame)
+    #[arg(long, default_value_t = 0.0)]
+    pub fps: f32,
+
+    /// Number of thumbnails per row
+    #[arg(long, default_value_t = 4)]
+    pub columns: usize,
+
+    /// Number of spaces between thumbnails
+    #[arg(long, default_value_t = 2)]
+    pub gap: usize,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct InspectArgs {
+    /// Script file path
+    #[arg(value_name = "SCRIPT")]
+    pub script: String,
+
+    /// Print the braille frames embedded in the script instead of the metadata
+    #[arg(long)]
+    pub frames: bool,
+}
+
+/// --variant のオプション文字列をパースする。指定された項目のみ値を持つ。
+#[derive(Parser, Debug)]
+#[command(no_binary_name = true)]
+struct VariantParser {
+    #[arg(short, long)]
+    size: Option<Size>,
+    #[arg(long)]
+    contrast: Option<ContrastOption>,
+    #[arg(long)]
+    invert: bool,
+    #[arg(long)]
+    binarize: Option<BinarizeOption>,
+}
+
+impl RenderOptions {
+    /// "--binarize fsdith --invert" のようなオプション文字列で、指定された項目のみ上書きした設定を返す
+    pub fn with_variant(&self, variant: &str) -> Result<RenderOptions> {
+        let parsed = VariantParser::try_parse_from(variant.split_whitespace()).map_err(|e| {
+            // clap のエラーメッセージは Usage を含むため1行目のみ使う
+            let message = e.to_string();
+            let message = message.lines().next().unwrap_or_default().to_string();
+            BraillerError::InvalidArgument(format!("variant {:?}: {}", variant, message))
+        })?;
+        Ok(RenderOptions {
+            size: parsed.size.unwrap_or(self.size),
+            contrast: parsed.contrast.unwrap_or(self.contrast),
+            invert: parsed.invert || self.invert,
+            binarize: parsed.binarize.unwrap_or(self.binarize),
+        })
+    }
+}
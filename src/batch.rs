@@ -0,0 +1,196 @@
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::args::Args;
+use crate::error::{BraillerError, Result};
+use crate::export;
+use crate::file_type::{self, FileType};
+use crate::image_processing::render_braille;
+use crate::input;
+use crate::scriptify::{ScriptOptions, ScriptSource};
+
+/// 複数入力・ディレクトリ・グロブのいずれかが指定されていればバッチモードで処理する
+pub fn is_batch(args: &Args) -> bool {
+    !args.output_dir.is_empty()
+        || args.input.len() > 1
+        || args
+            .input
+            .iter()
+            .any(|path| is_glob(path) || Path::new(path).is_dir())
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+/// `*` と `?` のみをサポートする簡易的なワイルドカードマッチ
+pub fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|i| wildcard_match(rest, &name[i..])),
+        Some(('?', rest)) => !name.is_empty() && wildcard_match(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && wildcard_match(rest, &name[1..]),
+    }
+}
+
+/// ディレクトリ内の画像ファイルのうち、ファイル名が pattern に一致するものを列挙する
+fn list_images(dir: &Path, pattern: Option<&str>) -> Result<Vec<PathBuf>> {
+    let pattern: Option<Vec<char>> = pattern.map(|p| p.chars().collect());
+    let mut paths = Vec::new();
+    let entries = fs::read_dir(dir).map_err(|e| BraillerError::io(dir.display(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| BraillerError::io(dir.display(), e))?
+            .path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(pattern) = &pattern {
+            let name: Vec<char> = path
+                .file_name()
+                .map(|name| name.to_string_lossy().chars().collect())
+                .unwrap_or_default();
+            if !wildcard_match(pattern, &name) {
+                continue;
+            }
+        }
+        // ヘッダーのみで判定するため、アニメーションも Image になる (最初のフレームを変換する)
+        if file_type::infer_type(&path.to_string_lossy()) == FileType::Image {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// 入力パスのリストを、ディレクトリとグロブを展開したファイルパスのリストに変換する
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        if input::is_stdin(input) {
+            return Err(BraillerError::InvalidArgument(
+                "stdin input cannot be used in batch mode".to_string(),
+            ));
+        } else if is_glob(input) {
+            let dir = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            if is_glob(&dir.to_string_lossy()) {
+                return Err(BraillerError::InvalidArgument(format!(
+                    "wildcards are only supported in file names: {}",
+                    input
+                )));
+            }
+            let pattern = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            paths.extend(list_images(dir, Some(&pattern))?);
+        } else if path.is_dir() {
+            paths.extend(list_images(path, None)?);
+        } else {
+            paths.push(path.to_path_buf());
+        }
+    }
+    Ok(paths)
+}
+
+/// 出力ファイル名テンプレートの {stem} と {name} を入力ファイル名で置換する
+pub fn output_path(template: &str, input: &Path, output_dir: &str) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = input
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = template.replace("{stem}", &stem).replace("{name}", &name);
+    Path::new(output_dir).join(file_name)
+}
+
+/// すべての入力の出力パスを求める。異なる入力の出力パスが重なる場合はエラーにする
+pub fn output_paths(template: &str, inputs: &[PathBuf], output_dir: &str) -> Result<Vec<PathBuf>> {
+    let mut seen: HashMap<PathBuf, &Path> = HashMap::new();
+    let mut outputs = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let output = output_path(template, input, output_dir);
+        if let Some(other) = seen.insert(output.clone(), input) {
+            return Err(BraillerError::InvalidArgument(format!(
+                "{} and {} would both be written to {}; use {{name}} in --name-template",
+                other.display(),
+                input.display(),
+                output.display()
+            )));
+        }
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
+
+/// 画像ファイル1つを変換して出力ファイルに保存する。
+/// command_line はスクリプトのメタデータに書き出すコマンドライン引数
+fn convert_file(input: &Path, output: &Path, args: &Args, command_line: &str) -> Result<()> {
+    let data = fs::read(input).map_err(|e| BraillerError::io(input.display(), e))?;
+    let img = image::load_from_memory(&data)?.to_luma8();
+    let braille = render_braille(&img, &args.render)?;
+    let path = output.to_string_lossy();
+    let format = export::OutputFormat::resolve(args.format, &path)?;
+    let mut script_options = ScriptOptions::new(args);
+    if format == export::OutputFormat::Script {
+        let source = ScriptSource::new(&input.to_string_lossy(), &data, command_line);
+        script_options = script_options.with_source(source);
+    }
+    export::save_image_output(&braille, &path, format, &script_options)
+}
+
+/// すべての入力を同じオプションで並列に変換し、出力ディレクトリに保存する
+pub fn run_batch(args: &Args, command_line: &str) -> Result<()> {
+    if args.output_dir.is_empty() {
+        return Err(BraillerError::InvalidArgument(
+            "--output-dir is required when converting multiple inputs".to_string(),
+        ));
+    }
+    export::OutputFormat::resolve(args.format, &args.name_template)?;
+    let inputs = expand_inputs(&args.input)?;
+    let outputs = output_paths(&args.name_template, &inputs, &args.output_dir)?;
+    fs::create_dir_all(&args.output_dir).map_err(|e| BraillerError::io(&args.output_dir, e))?;
+
+    let failures = inputs
+        .par_iter()
+        .zip(&outputs)
+        .filter_map(
+            |(input, output)| match convert_file(input, output, args, command_line) {
+                Ok(()) => {
+                    if args.verbose {
+                        eprintln!("{} -> {}", input.display(), output.display());
+                    }
+                    None
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", input.display(), e);
+                    Some(input)
+                }
+            },
+        )
+        .count();
+
+    if args.verbose {
+        eprintln!(
+            "Converted {} of {} files",
+            inputs.len() - failures,
+            inputs.len()
+        );
+    }
+    if failures > 0 {
+        return Err(BraillerError::BatchFailed {
+            failed: failures,
+            total: inputs.len(),
+        });
+    }
+    Ok(())
+}
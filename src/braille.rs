@@ -1,27 +1,56 @@
 pub use image::GrayImage;
 
-pub fn generate_braille(img: &GrayImage, cols: u32, rows: u32) -> String {
-    let width = img.width();
+use crate::error::{BraillerError, Result};
+
+/// Brailleセル内のドット位置と対応するビット: (dx, dy, ブライルビット)
+pub const DOT_BITS: [(u32, u32, u8); 8] = [
+    (0, 0, 0x01),
+    (1, 0, 0x08),
+    (0, 1, 0x02),
+    (1, 1, 0x10),
+    (0, 2, 0x04),
+    (1, 2, 0x20),
+    (0, 3, 0x40),
+    (1, 3, 0x80),
+];
+
+/// Braille文字のドットパターン（8ビット）を返す。Braille文字でなければ None。
+pub fn braille_bits(c: char) -> Option<u8> {
+    let code = c as u32;
+    if (0x2800..=0x28FF).contains(&code) {
+        Some((code - 0x2800) as u8)
+    } else {
+        None
+    }
+}
+
+/// 二値化済み画像を cols x rows 文字のBraille文字列に変換する。
+///
+/// 画像サイズが 2 の倍数・4 の倍数でない場合、はみ出したセルの不足ドットは off として扱う。
+/// 指定したセル数が画像に収まらない場合はエラーを返す。
+pub fn generate_braille(img: &GrayImage, cols: u32, rows: u32) -> Result<String> {
+    let (width, height) = img.dimensions();
+    let (max_cols, max_rows) = (width.div_ceil(2), height.div_ceil(4));
+    if cols > max_cols || rows > max_rows {
+        return Err(BraillerError::InvalidSize(format!(
+            "Braille size {}x{} does not fit the {}x{} image (at most {}x{})",
+            cols, rows, width, height, max_cols, max_rows
+        )));
+    }
     let buffer = img.as_raw();
     let mut output = String::with_capacity((cols * rows + rows) as usize);
-    // タプル: (dx, dy, ブライルビット)
-    let offsets = [
-        (0, 0, 0x01),
-        (1, 0, 0x08),
-        (0, 1, 0x02),
-        (1, 1, 0x10),
-        (0, 2, 0x04),
-        (1, 2, 0x20),
-        (0, 3, 0x40),
-        (1, 3, 0x80),
-    ];
     for cell_y in 0..rows {
         let base_y = cell_y * 4;
         for cell_x in 0..cols {
             let base_x = cell_x * 2;
             let mut braille_value = 0u8;
-            for &(dx, dy, bit) in offsets.iter() {
-                let idx = ((base_y + dy) * width + base_x + dx) as usize;
+            for &(dx, dy, bit) in DOT_BITS.iter() {
+                let (x, y) = (base_x + dx, base_y + dy);
+                // 画像外のドットは off
+                if x >= width || y >= height {
+                    continue;
+                }
+                let idx = (y * width + x) as usize;
                 if buffer[idx] < 128 {
                     braille_value |= bit;
                 }
@@ -30,7 +59,7 @@ pub fn generate_braille(img: &GrayImage, cols: u32, rows: u32) -> String {
         }
         output.push('\n');
     }
-    output
+    Ok(output)
 }
 
 pub fn convert_size(w: u32, h: u32, _cols: u32, _rows: u32) -> (u32, u32) {
@@ -1,8 +1,11 @@
+use crate::error::{BraillerError, Result};
+
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 /// 各dotをu8で表現するキャンバス。各要素は 0 (off) または 1 (on) を持つ。
+#[derive(Clone)]
 pub struct DotCanvas {
     width: usize,
     height: usize,
@@ -35,12 +38,12 @@ impl DotCanvas {
     }
 
     pub fn draw_line(&mut self, p1: Position, p2: Position) {
-        let dx = p2.x as i32 - p1.x as i32;
-        let dy = p2.y as i32 - p1.y as i32;
+        let dx = p2.x - p1.x;
+        let dy = p2.y - p1.y;
         let steps = dx.abs().max(dy.abs());
         for i in 0..=steps {
-            let x = p1.x as i32 + dx * i / steps;
-            let y = p1.y as i32 + dy * i / steps;
+            let x = p1.x + dx * i / steps;
+            let y = p1.y + dy * i / steps;
             self.set(x, y, 1);
         }
     }
@@ -112,21 +115,19 @@ impl DotCanvas {
             }
         }
     }
-    pub fn clone(&self) -> DotCanvas {
-        let mut new_canvas = DotCanvas::new(self.width, self.height);
-        for y in 0..self.height {
-            for x in 0..self.width {
-                new_canvas.set(x as i32, y as i32, self.get(x, y));
-            }
-        }
-        new_canvas
-    }
     /// 2x4ドット毎にグループ化してUnicode Braille文字に変換する。
     /// 出力はキャンバスのBrailleパターン文字列。
-    pub fn to_braille(&self) -> String {
+    /// 幅が2の倍数、高さが4の倍数でない場合、端のセルの不足ドットは off として扱う。
+    pub fn to_braille(&self) -> Result<String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(BraillerError::InvalidSize(format!(
+                "Cannot convert an empty {}x{} canvas to braille",
+                self.width, self.height
+            )));
+        }
         // Brailleセルは2x4ドット、キャンバス全体はセル数に換算
-        let cell_cols = self.width / 2;
-        let cell_rows = self.height / 4;
+        let cell_cols = self.width.div_ceil(2);
+        let cell_rows = self.height.div_ceil(4);
         let mut output = String::new();
 
         for cell_y in 0..cell_rows {
@@ -137,6 +138,10 @@ impl DotCanvas {
                     for dx in 0..2 {
                         let x = cell_x * 2 + dx;
                         let y = cell_y * 4 + dy;
+                        // キャンバス外のドットは off
+                        if x >= self.width || y >= self.height {
+                            continue;
+                        }
                         let dot = self.get(x, y);
                         // dotがオンの場合、対応するビットをセットする。
                         // 以下は一般的なBrailleの点番号のマッピング例:
@@ -163,7 +168,7 @@ impl DotCanvas {
             output.push('\n');
         }
 
-        output
+        Ok(output)
     }
 }
 
@@ -177,6 +182,6 @@ impl DotCanvas {
 //     }
 
 //     // Braille文字列に変換して出力
-//     let braille_art = canvas.to_braille();
+//     let braille_art = canvas.to_braille().unwrap();
 //     println!("{}", braille_art);
 // }
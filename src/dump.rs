@@ -0,0 +1,46 @@
+use image::GrayImage;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::{BraillerError, Result};
+
+/// --dump-frames で指定されたディレクトリに、処理途中のフレームを連番の PNG として保存する
+pub struct FrameDumper {
+    dir: PathBuf,
+    /// この間隔ごとにフレームを保存する
+    every: u32,
+    /// これまでに受け取ったフレーム数
+    count: u32,
+}
+
+impl FrameDumper {
+    /// --dump-frames の dir が空 (指定されていない) の場合は None を返す
+    pub fn new(dir: &str, every: u32) -> Result<Option<Self>> {
+        if dir.is_empty() {
+            return Ok(None);
+        }
+        fs::create_dir_all(dir).map_err(|e| BraillerError::io(dir, e))?;
+        Ok(Some(Self {
+            dir: PathBuf::from(dir),
+            every,
+            count: 0,
+        }))
+    }
+
+    /// 二値化前 (前処理・リサイズ後) と二値化後の画像を frame-{番号}-pre.png / frame-{番号}-post.png として保存する
+    pub fn dump(&mut self, pre: &GrayImage, post: &GrayImage) -> Result<()> {
+        let index = self.count;
+        self.count += 1;
+        if !index.is_multiple_of(self.every) {
+            return Ok(());
+        }
+        for (image, stage) in [(pre, "pre"), (post, "post")] {
+            let path = self.dir.join(format!("frame-{:06}-{}.png", index, stage));
+            image.save(&path).map_err(|e| match e {
+                image::ImageError::IoError(e) => BraillerError::io(path.display(), e),
+                e => e.into(),
+            })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use std::{fmt, io};
+
+/// brailler 全体で使うエラー
+#[derive(Debug)]
+pub enum BraillerError {
+    /// ファイルや端末の読み書きの失敗
+    Io(io::Error),
+    /// 画像・アニメーションのデコードの失敗
+    Decode(String),
+    /// 対応していない入力・出力形式
+    UnsupportedFormat(String),
+    /// ffmpeg による動画の読み込みの失敗
+    Ffmpeg(String),
+    /// 出力サイズが不正
+    InvalidSize(String),
+    /// オプションの組み合わせや値が不正
+    InvalidArgument(String),
+    /// バッチモードで一部のファイルの変換に失敗した
+    BatchFailed { failed: usize, total: usize },
+}
+
+pub type Result<T> = std::result::Result<T, BraillerError>;
+
+impl BraillerError {
+    /// パスを含めた I/O エラーを作る
+    pub fn io(path: impl fmt::Display, error: io::Error) -> Self {
+        BraillerError::Io(io::Error::new(error.kind(), format!("{}: {}", path, error)))
+    }
+
+    /// プロセスの終了コード (sysexits.h に合わせる)
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            BraillerError::InvalidSize(_) | BraillerError::InvalidArgument(_) => 64, // EX_USAGE
+            BraillerError::Decode(_) | BraillerError::UnsupportedFormat(_) => 65,    // EX_DATAERR
+            BraillerError::Ffmpeg(_) => 70,                                          // EX_SOFTWARE
+            BraillerError::Io(_) => 74,                                              // EX_IOERR
+            // 個々のファイルのエラーは変換時に表示済み
+            BraillerError::BatchFailed { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for BraillerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BraillerError::Io(e) => write!(f, "I/O error: {}", e),
+            BraillerError::Decode(message) => write!(f, "Failed to decode input: {}", message),
+            BraillerError::UnsupportedFormat(message) => {
+                write!(f, "Unsupported format: {}", message)
+            }
+            BraillerError::Ffmpeg(message) => write!(f, "ffmpeg error: {}", message),
+            BraillerError::InvalidSize(message) => write!(f, "Invalid size: {}", message),
+            BraillerError::InvalidArgument(message) => write!(f, "Invalid argument: {}", message),
+            BraillerError::BatchFailed { failed, total } => {
+                write!(f, "Failed to convert {} of {} files", failed, total)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BraillerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BraillerError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BraillerError {
+    fn from(error: io::Error) -> Self {
+        BraillerError::Io(error)
+    }
+}
+
+impl From<image::ImageError> for BraillerError {
+    fn from(error: image::ImageError) -> Self {
+        match error {
+            image::ImageError::IoError(e) => BraillerError::Io(e),
+            image::ImageError::Unsupported(e) => BraillerError::UnsupportedFormat(e.to_string()),
+            e => BraillerError::Decode(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "video")]
+impl From<ffmpeg_next::Error> for BraillerError {
+    fn from(error: ffmpeg_next::Error) -> Self {
+        BraillerError::Ffmpeg(error.to_string())
+    }
+}
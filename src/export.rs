@@ -0,0 +1,326 @@
+use clap::ValueEnum;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, GrayImage, Luma};
+use imageproc::drawing::draw_filled_circle_mut;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::braille::{DOT_BITS, braille_bits};
+use crate::error::{BraillerError, Result};
+use crate::scriptify::{self, ScriptOptions};
+use crate::terminal::{DiffRenderer, display_width};
+use crate::timeline::frame_interval;
+
+/// ラスタライズ時のドット間隔（ピクセル）
+pub const DOT_PITCH: u32 = 4;
+/// ラスタライズ時のドット半径（ピクセル）
+const DOT_RADIUS: i32 = 1;
+/// 端末風に黒背景に白いドットで描画する
+const BACKGROUND: u8 = 0;
+const FOREGROUND: u8 = 255;
+
+/// --output で指定できる出力形式。--format で指定しない場合は拡張子から判定する。
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Html,
+    Svg,
+    Png,
+    Ansi,
+    Script,
+    // asciinema の asciicast v2 形式
+    Asciicast,
+    // ドットを円として描画したアニメーション GIF
+    Gif,
+}
+
+/// タイムスタンプ付きのBraille文字列のフレーム
+pub struct TextFrame {
+    pub pts: Duration,
+    pub text: String,
+}
+
+impl OutputFormat {
+    /// format が指定されていればそれを、なければ path の拡張子から判定した形式を返す
+    pub fn resolve(format: Option<OutputFormat>, path: &str) -> Result<Self> {
+        match format {
+            Some(format) => Ok(format),
+            None => OutputFormat::from_path(path),
+        }
+    }
+
+    pub fn from_path(path: &str) -> Result<Self> {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "txt" => Ok(OutputFormat::Text),
+            "html" | "htm" => Ok(OutputFormat::Html),
+            "svg" => Ok(OutputFormat::Svg),
+            "png" => Ok(OutputFormat::Png),
+            "ans" => Ok(OutputFormat::Ansi),
+            "sh" | "fish" | "ps1" | "py" | "js" | "cjs" => Ok(OutputFormat::Script),
+            "cast" => Ok(OutputFormat::Asciicast),
+            "gif" => Ok(OutputFormat::Gif),
+            _ => Err(BraillerError::UnsupportedFormat(format!(
+                "unknown output file extension: {} (expected txt, html, svg, png, ans, sh, fish, ps1, py, js, cjs, cast or gif)",
+                path
+            ))),
+        }
+    }
+}
+
+/// ファイルに書き込む。エラーにはパスを含める
+fn write_file(path: &str, contents: impl AsRef<[u8]>) -> Result<()> {
+    fs::write(path, contents).map_err(|e| BraillerError::io(path, e))
+}
+
+/// Braille文字列を行ごとのドットパターンに分解する。Braille以外の文字は空セルとして扱う。
+fn to_cells(braille: &str) -> Vec<Vec<u8>> {
+    braille
+        .lines()
+        .map(|line| line.chars().map(|c| braille_bits(c).unwrap_or(0)).collect())
+        .collect()
+}
+
+/// on になっているドットの座標 (ドット単位) を列挙する
+fn on_dots(cells: &[Vec<u8>]) -> impl Iterator<Item = (u32, u32)> + '_ {
+    cells.iter().enumerate().flat_map(|(cell_y, line)| {
+        line.iter().enumerate().flat_map(move |(cell_x, &bits)| {
+            DOT_BITS
+                .iter()
+                .filter(move |&&(_, _, bit)| bits & bit != 0)
+                .map(move |&(dx, dy, _)| (cell_x as u32 * 2 + dx, cell_y as u32 * 4 + dy))
+        })
+    })
+}
+
+/// Braille文字列のドットを円として描画した画像を生成する（フォント不要）
+pub fn rasterize(braille: &str) -> GrayImage {
+    let cells = to_cells(braille);
+    let cols = cells.iter().map(|line| line.len()).max().unwrap_or(0) as u32;
+    let rows = cells.len() as u32;
+    let mut img = GrayImage::from_pixel(
+        cols * 2 * DOT_PITCH,
+        rows * 4 * DOT_PITCH,
+        Luma([BACKGROUND]),
+    );
+    let offset = (DOT_PITCH / 2) as i32;
+    for (x, y) in on_dots(&cells) {
+        let center = (
+            (x * DOT_PITCH) as i32 + offset,
+            (y * DOT_PITCH) as i32 + offset,
+        );
+        draw_filled_circle_mut(&mut img, center, DOT_RADIUS, Luma([FOREGROUND]));
+    }
+    img
+}
+
+/// Braille文字列のドットを circle 要素として並べたSVGを生成する
+pub fn to_svg(braille: &str) -> String {
+    let cells = to_cells(braille);
+    let cols = cells.iter().map(|line| line.len()).max().unwrap_or(0) as u32;
+    let rows = cells.len() as u32;
+    let (width, height) = (cols * 2 * DOT_PITCH, rows * 4 * DOT_PITCH);
+    let offset = DOT_PITCH as f32 / 2.0;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"black\"/>\n\
+         <g fill=\"white\">\n"
+    );
+    for (x, y) in on_dots(&cells) {
+        svg.push_str(&format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\"/>\n",
+            (x * DOT_PITCH) as f32 + offset,
+            (y * DOT_PITCH) as f32 + offset,
+            DOT_RADIUS as f32 + 0.5
+        ));
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// Braille文字列を pre 要素で表示するHTMLを生成する。
+/// 字幕などの Braille 以外の文字が含まれる場合に備え、HTML の特殊文字はエスケープする
+pub fn to_html(braille: &str) -> String {
+    let text = braille
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>brailler</title>\n\
+         </head>\n\
+         <body style=\"background: black; color: white;\">\n\
+         <pre style=\"line-height: 1; font-family: monospace;\">\n{}</pre>\n\
+         </body>\n\
+         </html>\n",
+        text
+    )
+}
+
+/// 画面クリア後に最初のフレームを描画し、以降のフレームは変更されたセルのみを書き換える
+/// ANSIエスケープ付きテキストを生成する
+pub fn to_ansi(frames: &[String]) -> String {
+    let mut renderer = DiffRenderer::new(false);
+    frames.iter().map(|frame| renderer.render(frame)).collect()
+}
+
+/// 文字列を JSON の文字列リテラルにする
+pub fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// フレームを asciicast v2 形式 (JSON のヘッダー行と、[時刻, "o", 出力] のイベント行) にする。
+/// 各フレームは ANSI 出力と同様に変更されたセルのみを書き換える。
+pub fn to_asciicast(frames: &[TextFrame]) -> String {
+    let width = frames
+        .iter()
+        .flat_map(|frame| frame.text.lines())
+        .map(display_width)
+        .max()
+        .unwrap_or(0);
+    // フレームの下にカーソルを置くため1行多くする
+    let height = frames
+        .iter()
+        .map(|frame| frame.text.lines().count())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default();
+    let mut cast = format!(
+        "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}, \"title\": \"brailler\"}}\n",
+        width, height, timestamp
+    );
+    let mut renderer = DiffRenderer::new(false);
+    for frame in frames {
+        // 記録された出力は端末の改行変換を経ないため、改行を CR LF にする
+        let output = renderer.render(&frame.text).replace('\n', "\r\n");
+        // 前のフレームから変化がない場合は出力するものがない
+        if output.is_empty() {
+            continue;
+        }
+        let _ = writeln!(
+            cast,
+            "[{:.6}, \"o\", {}]",
+            frame.pts.as_secs_f64(),
+            json_string(&output)
+        );
+    }
+    cast
+}
+
+/// 各フレームをラスタライズし、pts どおりのタイミングで繰り返し再生するアニメーション GIF として保存する。
+/// 最後のフレームは 1/fps 秒表示する。
+pub fn save_gif(frames: &[TextFrame], fps: f32, path: &str) -> Result<()> {
+    let last_delay = frame_interval(fps)?;
+    let file = File::create(path).map_err(|e| BraillerError::io(path, e))?;
+    let mut encoder = GifEncoder::new(BufWriter::new(file));
+    encoder.set_repeat(Repeat::Infinite)?;
+    // GIF の表示時間は 10ms 単位のため、丸め誤差が蓄積しないよう pts を丸めてから差を取る
+    let centis = |pts: Duration| (pts.as_secs_f64() * 100.0).round() as u64;
+    for (i, frame) in frames.iter().enumerate() {
+        let next = frames
+            .get(i + 1)
+            .map(|next| next.pts)
+            .unwrap_or(frame.pts + last_delay);
+        let delay = Duration::from_millis(centis(next).saturating_sub(centis(frame.pts)) * 10);
+        let image = DynamicImage::ImageLuma8(rasterize(&frame.text)).to_rgba8();
+        encoder.encode_frame(image::Frame::from_parts(
+            image,
+            0,
+            0,
+            Delay::from_saturating_duration(delay),
+        ))?;
+    }
+    Ok(())
+}
+
+/// 画像1枚分のBraille文字列を、指定された形式でファイルに保存する
+/// script はスクリプト形式の場合の設定
+pub fn save_image_output(
+    braille: &str,
+    path: &str,
+    format: OutputFormat,
+    script: &ScriptOptions,
+) -> Result<()> {
+    match format {
+        OutputFormat::Text => write_file(path, braille)?,
+        OutputFormat::Html => write_file(path, to_html(braille))?,
+        OutputFormat::Svg => write_file(path, to_svg(braille))?,
+        OutputFormat::Png => rasterize(braille).save_with_format(path, image::ImageFormat::Png)?,
+        OutputFormat::Ansi => write_file(path, to_ansi(&[braille.to_string()]))?,
+        OutputFormat::Script => {
+            let script = scriptify::generate_script_for_image(braille, script)?;
+            scriptify::save_script(&script, path)?;
+        }
+        OutputFormat::Asciicast => {
+            let frame = TextFrame {
+                pts: Duration::ZERO,
+                text: braille.to_string(),
+            };
+            write_file(path, to_asciicast(&[frame]))?
+        }
+        // GIF エンコーダはグレースケールに対応していないため RGBA にする
+        OutputFormat::Gif => DynamicImage::ImageLuma8(rasterize(braille))
+            .to_rgba8()
+            .save_with_format(path, image::ImageFormat::Gif)?,
+    }
+    Ok(())
+}
+
+/// 動画の各フレームのBraille文字列を、指定された形式でファイルに保存する
+pub fn save_video_output(
+    frames: &[TextFrame],
+    fps: f32,
+    path: &str,
+    format: OutputFormat,
+    script: &ScriptOptions,
+) -> Result<()> {
+    let texts: Vec<String> = frames.iter().map(|frame| frame.text.clone()).collect();
+    match format {
+        // フレーム間は空行で区切る
+        OutputFormat::Text => write_file(path, texts.join("\n"))?,
+        OutputFormat::Ansi => write_file(path, to_ansi(&texts))?,
+        OutputFormat::Script => {
+            let script = scriptify::generate_script_for_video(frames, fps, script)?;
+            scriptify::save_script(&script, path)?;
+        }
+        OutputFormat::Asciicast => write_file(path, to_asciicast(frames))?,
+        OutputFormat::Gif => save_gif(frames, fps, path)?,
+        OutputFormat::Html | OutputFormat::Svg | OutputFormat::Png => {
+            return Err(BraillerError::UnsupportedFormat(format!(
+                "output format {:?} does not support video",
+                format
+            )));
+        }
+    }
+    Ok(())
+}
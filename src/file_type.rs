@@ -1,23 +1,38 @@
-use std::fs;
+use crate::animation;
 
 // 画像か動画の判定結果
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileType {
     Image,
+    /// アニメーション GIF / APNG / WebP
+    Animation,
     Video,
     Unknown,
 }
 
+/// ファイル先頭のヘッダーだけを読んで種別を判定する。
+/// アニメーションかどうかはデコードしないと分からないため、画像はすべて Image になる
 pub fn infer_type(filepath: &str) -> FileType {
+    match infer::get_from_path(filepath) {
+        Ok(Some(kind)) if kind.mime_type().starts_with("image/") => FileType::Image,
+        Ok(Some(kind)) if kind.mime_type().starts_with("video/") => FileType::Video,
+        _ => FileType::Unknown,
+    }
+}
+
+/// メモリ上のデータ（標準入力など）からファイル種別を判定する
+pub fn infer_type_from_bytes(buf: &[u8]) -> FileType {
     let mut file_type = FileType::Unknown;
-    if let Ok(buf) = fs::read(filepath) {
-        if let Some(kind) = infer::get(&buf) {
-            if kind.mime_type().starts_with("image/") {
-                file_type = FileType::Image;
-            } else if kind.mime_type().starts_with("video/") {
-                file_type = FileType::Video;
-            }
-        };
+    if let Some(kind) = infer::get(buf) {
+        if kind.mime_type().starts_with("image/") {
+            file_type = if animation::is_animated(buf) {
+                FileType::Animation
+            } else {
+                FileType::Image
+            };
+        } else if kind.mime_type().starts_with("video/") {
+            file_type = FileType::Video;
+        }
     }
     file_type
 }
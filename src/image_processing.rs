@@ -4,6 +4,8 @@ use imageproc::contrast::equalize_histogram;
 use imageproc::contrast::otsu_level;
 
 use crate::args;
+use crate::braille::{convert_size, generate_braille};
+use crate::error::{BraillerError, Result};
 use crate::measure_time;
 
 // 画像の色反転
@@ -124,6 +126,68 @@ pub fn binarize_with_otsu(input: &GrayImage) -> GrayImage {
     output
 }
 
+/// 時間方向に安定したディザリングで、前フレームの値を反転させるのに必要な
+/// しきい値からの輝度の差
+pub const STABLE_HYSTERESIS: f32 = 24.0;
+
+/// 画素ごとに固定のしきい値 (Interleaved Gradient Noise)。
+/// ブルーノイズに近い分布で、フレーム間で変わらないためディザパターンがちらつかない
+pub fn stable_threshold(x: u32, y: u32) -> f32 {
+    let noise = (52.982_918 * (0.067_110_56 * x as f32 + 0.005_837_15 * y as f32).fract()).fract();
+    noise * 254.0 + 0.5
+}
+
+/// 固定のしきい値マスクで二値化する関数
+///
+/// previous に前フレームの結果を指定した場合は、輝度がしきい値から STABLE_HYSTERESIS 以上
+/// 離れた画素のみ値を変えるため、静止した領域のドットが揺れない。
+pub fn stable_dither(input: &GrayImage, previous: Option<&GrayImage>) -> GrayImage {
+    let (width, height) = input.dimensions();
+    let mut output = GrayImage::new(width, height);
+    for (x, y, pixel) in input.enumerate_pixels() {
+        let intensity = pixel[0] as f32;
+        let threshold = stable_threshold(x, y);
+        let on = match previous {
+            // 白・黒の画素は必ず反転できるよう、しきい値を 1..=254 に収める
+            Some(previous) if previous.get_pixel(x, y)[0] > 0 => {
+                intensity >= (threshold - STABLE_HYSTERESIS).max(1.0)
+            }
+            Some(_) => intensity > (threshold + STABLE_HYSTERESIS).min(254.0),
+            None => intensity > threshold,
+        };
+        output.put_pixel(x, y, Luma([if on { 255 } else { 0 }]));
+    }
+    output
+}
+
+/// 動画のフレームを順に二値化する。Stable の場合は前フレームの結果を引き継ぐ。
+pub struct FrameBinarizer {
+    option: args::BinarizeOption,
+    previous: Option<GrayImage>,
+}
+
+impl FrameBinarizer {
+    pub fn new(option: args::BinarizeOption) -> Self {
+        Self {
+            option,
+            previous: None,
+        }
+    }
+
+    pub fn binarize(&mut self, input: &GrayImage) -> GrayImage {
+        if self.option != args::BinarizeOption::Stable {
+            return binarize(input, self.option);
+        }
+        let previous = self
+            .previous
+            .as_ref()
+            .filter(|previous| previous.dimensions() == input.dimensions());
+        let output = stable_dither(input, previous);
+        self.previous = Some(output.clone());
+        output
+    }
+}
+
 #[allow(clippy::let_and_return)]
 // 画像処理パイプライン
 pub fn preprocess_image(
@@ -156,12 +220,15 @@ pub fn binarize(input: &GrayImage, binarize_opt: args::BinarizeOption) -> GrayIm
         floyd_steinberg_dither(&img)
     } else if binarize_opt == args::BinarizeOption::Otsu {
         binarize_with_otsu(&img)
+    } else if binarize_opt == args::BinarizeOption::Stable {
+        stable_dither(&img, None)
     } else {
         img
     };
     img
 }
 
+#[allow(clippy::let_and_return)]
 pub fn process_image(
     img: &GrayImage,
     cols: u32,
@@ -169,26 +236,39 @@ pub fn process_image(
     contrast_opt: args::ContrastOption,
     invert_opt: bool,
     binarize_opt: args::BinarizeOption,
-) -> GrayImage {
-    let img = measure_time!(preprocess_image(&img, contrast_opt, invert_opt,));
+) -> Result<GrayImage> {
+    if cols == 0 || rows == 0 {
+        return Err(BraillerError::InvalidSize(format!(
+            "Output size {}x{} has no cells",
+            cols, rows
+        )));
+    }
+    let img = measure_time!(preprocess_image(img, contrast_opt, invert_opt,));
     // リサイズしてキャンバスに貼り付け
     let (width, height) = (cols * 2, rows * 4);
     let img = measure_time!(resize(&img, width, height, FilterType::Nearest));
     // ピクセルを二値化する
     let img = measure_time!(binarize(&img, binarize_opt));
-    img
+    Ok(img)
+}
+
+/// 前処理・リサイズ・二値化を行い、Braille文字列に変換する
+pub fn render_braille(img: &GrayImage, opts: &args::RenderOptions) -> Result<String> {
+    let (w, h) = img.dimensions();
+    let (cols, rows) = convert_size(w, h, opts.size.0, opts.size.1);
+    let img = process_image(img, cols, rows, opts.contrast, opts.invert, opts.binarize)?;
+    generate_braille(&img, cols, rows)
 }
 
 // 画像をファイルに保存
-pub fn save_image(img: &GrayImage, path: &str) {
-    img.save(path).expect("画像の保存に失敗しました");
+pub fn save_image(img: &GrayImage, path: &str) -> Result<()> {
+    img.save(path)?;
+    Ok(())
 }
 
 // キャンバスに画像を貼り付け
-pub fn put_image_into_canvas(img: &GrayImage, width: u32, height: u32) -> GrayImage {
+pub fn put_image_into_canvas(img: &GrayImage, width: u32, height: u32) -> Result<GrayImage> {
     let mut canvas: GrayImage = ImageBuffer::from_pixel(width, height, Luma([255u8]));
-    canvas
-        .copy_from(img, 0, 0)
-        .expect("画像の貼り付けに失敗しました");
-    canvas
+    canvas.copy_from(img, 0, 0)?;
+    Ok(canvas)
 }
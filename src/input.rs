@@ -0,0 +1,95 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{BraillerError, Result};
+
+/// 標準入力から読み込むことを表す入力パス
+pub const STDIN_PATH: &str = "-";
+
+/// 入力パスが標準入力を指しているかを判定する
+pub fn is_stdin(path: &str) -> bool {
+    path == STDIN_PATH
+}
+
+/// 入力パスの内容をすべて読み込む。"-" の場合は標準入力から読み込む。
+pub fn read_input(path: &str) -> Result<Vec<u8>> {
+    if is_stdin(path) {
+        let mut buf = Vec::new();
+        io::stdin()
+            .lock()
+            .read_to_end(&mut buf)
+            .map_err(|e| BraillerError::io("stdin", e))?;
+        Ok(buf)
+    } else {
+        fs::read(path).map_err(|e| BraillerError::io(path, e))
+    }
+}
+
+/// ffmpeg などファイルパスが必要な処理に渡すパス。
+/// 標準入力の場合は読み込んだ data を一時ファイルに書き出し、そのパスを返す。
+/// 一時ファイルは返した TempInput が Drop されるまで残る。
+/// ファイルの場合は data を使わず、path をそのまま返す
+pub fn file_path(path: &str, data: Option<&[u8]>) -> Result<(String, Option<TempInput>)> {
+    match data {
+        Some(data) if is_stdin(path) => {
+            let temp = TempInput::new(data)?;
+            Ok((temp.path().to_string_lossy().into_owned(), Some(temp)))
+        }
+        _ => Ok((path.to_string(), None)),
+    }
+}
+
+/// ファイルパスが必要な処理（ffmpeg など）のために、メモリ上のデータを書き出した一時ファイル。
+/// Drop 時に削除される。
+pub struct TempInput {
+    path: PathBuf,
+}
+
+impl TempInput {
+    /// 推測されにくい名前で新しいファイルを作成する。
+    /// 既存のファイル (他のユーザーが置いたシンボリックリンクなど) は開かず、別の名前で作り直す
+    pub fn new(data: &[u8]) -> io::Result<Self> {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        loop {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default();
+            let name = format!(
+                "brailler-stdin-{}-{:x}-{}",
+                std::process::id(),
+                nanos,
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let path = std::env::temp_dir().join(name);
+            let mut file = match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+            // 書き込みに失敗した場合も Drop で削除する
+            let temp = Self { path };
+            file.write_all(data)?;
+            return Ok(temp);
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempInput {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,95 @@
+use base64::{Engine as _, engine::general_purpose};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::fs;
+use std::io::Read;
+use std::time::Duration;
+use xz2::read::XzDecoder;
+
+use crate::args::InspectArgs;
+use crate::error::{BraillerError, Result};
+use crate::export::TextFrame;
+use crate::scriptify::{self, FRAME_SEPARATOR, PAYLOAD_END, PAYLOAD_START};
+
+/// PAYLOAD_START と PAYLOAD_END の間から、Base64 のテキストを取り出してデコードする。
+/// 引用符で囲まれている場合 (fish / PowerShell / Python / JavaScript) はその中身、
+/// そうでない場合 (sh / bash のヒアドキュメント) は行全体を Base64 として扱う。
+fn extract_payload(script: &str) -> Option<Vec<u8>> {
+    script
+        .lines()
+        .skip_while(|line| !line.ends_with(PAYLOAD_START))
+        .skip(1)
+        .take_while(|line| !line.ends_with(PAYLOAD_END))
+        .filter_map(|line| {
+            let line = line.trim();
+            let quoted = line.find(['\'', '"']).and_then(|start| {
+                let quote = line[start..].chars().next()?;
+                let end = line.rfind(quote)?;
+                (start < end).then(|| &line[start + 1..end])
+            });
+            general_purpose::STANDARD
+                .decode(quoted.unwrap_or(line))
+                .ok()
+        })
+        .find(|data| !data.is_empty())
+}
+
+/// 先頭のマジックナンバーから圧縮形式を判定して展開する
+fn decompress(data: &[u8]) -> Result<String> {
+    let mut bytes = Vec::new();
+    match data {
+        [0x1f, 0x8b, ..] => {
+            GzDecoder::new(data).read_to_end(&mut bytes)?;
+        }
+        [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => {
+            XzDecoder::new(data).read_to_end(&mut bytes)?;
+        }
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => bytes = zstd::decode_all(data)?,
+        // zlib 形式 (Python / Node.js の gzip)
+        [0x78, flags, ..] if (0x78 * 256 + *flags as u32).is_multiple_of(31) => {
+            ZlibDecoder::new(data).read_to_end(&mut bytes)?;
+        }
+        _ => bytes.extend_from_slice(data),
+    }
+    String::from_utf8(bytes).map_err(|e| BraillerError::Decode(e.to_string()))
+}
+
+/// スクリプトに埋め込まれたフレームを取り出す。画像のスクリプトは1フレームになる
+pub fn extract_frames(script: &str) -> Result<Vec<TextFrame>> {
+    let data = extract_payload(script)
+        .ok_or_else(|| BraillerError::Decode("no braille text found in the script".to_string()))?;
+    let text = decompress(&data)?;
+    if text.starts_with(FRAME_SEPARATOR) {
+        scriptify::decode_frames(&text)
+    } else {
+        Ok(vec![TextFrame {
+            pts: Duration::ZERO,
+            text,
+        }])
+    }
+}
+
+/// スクリプトのメタデータ、または --frames の場合は埋め込まれたフレームを表示する
+pub fn run_inspect(args: &InspectArgs) -> Result<()> {
+    let script =
+        fs::read_to_string(&args.script).map_err(|e| BraillerError::io(&args.script, e))?;
+    if args.frames {
+        // フレーム間は空行で区切る
+        let texts: Vec<String> = extract_frames(&script)?
+            .into_iter()
+            .map(|frame| frame.text)
+            .collect();
+        print!("{}", texts.join("\n"));
+        return Ok(());
+    }
+    let metadata = scriptify::read_metadata(&script);
+    if metadata.is_empty() {
+        return Err(BraillerError::Decode(format!(
+            "{} has no brailler metadata",
+            args.script
+        )));
+    }
+    for (key, value) in metadata {
+        println!("{}: {}", key, value);
+    }
+    Ok(())
+}
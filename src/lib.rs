@@ -1,12 +1,26 @@
 mod utilities;
 
+pub mod animation;
 pub mod args;
+pub mod batch;
 pub mod braille;
 pub mod dot_canvas;
+pub mod dump;
+pub mod error;
+pub mod export;
 pub mod file_type;
 pub mod image_processing;
+pub mod input;
+pub mod inspect;
+pub mod montage;
+pub mod player;
 pub mod scriptify;
 pub mod size;
+pub mod subtitles;
+pub mod terminal;
+pub mod thumbnails;
+pub mod timeline;
+pub mod timestamp;
 
 #[cfg(feature = "video")]
 pub mod video;
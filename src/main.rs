@@ -1,30 +1,54 @@
-mod size;
 mod utilities;
 
 use braille::*;
 use clap::Parser;
 use image::{self, GrayImage};
-use std::error::Error;
-use std::io;
-use std::io::Write;
+use std::process::ExitCode;
 
+use brailler::animation;
 use brailler::args;
+use brailler::batch;
 use brailler::braille;
+use brailler::dump;
+use brailler::error::{BraillerError, Result};
+use brailler::export;
 use brailler::file_type;
 use brailler::image_processing;
-use brailler::scriptify;
+use brailler::input;
+use brailler::inspect;
+use brailler::montage;
+use brailler::player::{self, FrameRenderer, FrameSource};
+use brailler::scriptify::{self, ScriptOptions, ScriptSource};
+use brailler::subtitles::Subtitles;
+use brailler::thumbnails;
+use brailler::timeline::Timeline;
 
 #[cfg(feature = "video")]
 use brailler::video;
 
 #[cfg(not(feature = "video"))]
-pub fn process_video(ftype: file_type::FileType, img_path: &str, args: args::Args) {
-    eprintln!("動画処理は無効です")
+pub fn process_video(
+    _ftype: file_type::FileType,
+    img_path: &str,
+    _data: Option<&[u8]>,
+    _args: args::Args,
+) -> Result<()> {
+    Err(BraillerError::UnsupportedFormat(format!(
+        "{} is a video, but video support is disabled (rebuild with --features video)",
+        img_path
+    )))
 }
 #[cfg(feature = "video")]
-pub fn process_video(ftype: file_type::FileType, img_path: &str, args: args::Args) {
+pub fn process_video(
+    ftype: file_type::FileType,
+    img_path: &str,
+    data: Option<&[u8]>,
+    args: args::Args,
+) -> Result<()> {
+    // ffmpeg はファイルパスを必要とするため、標準入力は一時ファイルに書き出す
+    let (video_path, _temp_input) = input::file_path(img_path, data)?;
     // 動画の処理
-    let video_data = video::load_frames(&img_path, args.clone())?;
+    let video_data = video::load_frames(&video_path, args::FrameOptions::new(&args)?)?;
     let (cols, rows) = (video_data.size.0 / 2, video_data.size.1 / 4);
 
     // 動画のフレーム数が0の場合は終了
@@ -33,62 +57,191 @@ pub fn process_video(ftype: file_type::FileType, img_path: &str, args: args::Arg
         eprintln!("Input: {}", img_path);
         eprintln!("File type: {:?}", ftype);
         eprintln!("Ratio: {}", video_data.ratio);
-        eprintln!("Specified Size: {:?}", args.size);
+        eprintln!("Specified Size: {:?}", args.render.size);
         eprintln!("Image size: {}", video_data.size);
         eprintln!("Image FPS: {}", video_data.fps);
         eprintln!("Cols: {}, Rows: {}", cols, rows);
     }
 
-    // 平均待機時間を計算
-    let avg_wait = std::time::Duration::from_secs_f32(1.0 / video_data.fps);
-
-    // 動画のフレームを処理
-    if args.scriptify.is_empty() {
-        for img in video_data.frames {
-            // 画像処理
-            let start = std::time::Instant::now();
-            let output = measure_time!(generate_braille(&img, cols, rows));
-            print!("\x1B[2J\x1B[1;1H");
-            io::stdout().flush().unwrap();
-            println!("{}", output);
-            io::stdout().flush().unwrap();
-            // sleep
-            let elapsed = std::time::Instant::now().duration_since(start);
-            if elapsed < avg_wait {
-                std::thread::sleep(avg_wait - elapsed);
+    let script_options = script_options(&args, img_path, data)?;
+    render_frames(
+        video_data.frames,
+        video_data.fps,
+        cols,
+        rows,
+        &args,
+        &script_options,
+    )
+}
+
+/// アニメーション GIF / APNG / WebP を動画と同様に再生・出力する
+fn process_animation(
+    ftype: file_type::FileType,
+    img_path: &str,
+    data: &[u8],
+    args: &args::Args,
+) -> Result<()> {
+    let animation_data = animation::load_frames(data, &args::FrameOptions::new(args)?)?;
+    let (cols, rows) = (animation_data.size.0 / 2, animation_data.size.1 / 4);
+    if args.verbose {
+        eprintln!("{:?}", args);
+        eprintln!("Input: {}", img_path);
+        eprintln!("File type: {:?}", ftype);
+        eprintln!("Ratio: {}", animation_data.ratio);
+        eprintln!("Specified Size: {:?}", args.render.size);
+        eprintln!("Image size: {}", animation_data.size);
+        eprintln!("Image FPS: {}", animation_data.fps);
+        eprintln!("Cols: {}, Rows: {}", cols, rows);
+    }
+    let script_options = script_options(args, img_path, Some(data))?;
+    render_frames(
+        animation_data.frames,
+        animation_data.fps,
+        cols,
+        rows,
+        args,
+        &script_options,
+    )
+}
+
+/// コマンドライン引数。空白を含む引数は引用符で囲む
+fn command_line() -> String {
+    std::env::args()
+        .skip(1)
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("'{}'", arg)
+            } else {
+                arg
             }
-        }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// スクリプト出力の設定。入力の SHA-256 などのメタデータは、スクリプトを出力する場合のみ計算する。
+/// data が None の場合 (読み込んでいない動画ファイル) はファイルから計算する
+fn script_options(args: &args::Args, path: &str, data: Option<&[u8]>) -> Result<ScriptOptions> {
+    let options = ScriptOptions::new(args);
+    let writes_script = !args.scriptify.is_empty()
+        || (!args.output.is_empty()
+            && export::OutputFormat::resolve(args.format, &args.output)?
+                == export::OutputFormat::Script);
+    if writes_script {
+        let source = match data {
+            Some(data) => ScriptSource::new(path, data, &command_line()),
+            None => ScriptSource::from_file(path, &command_line())?,
+        };
+        Ok(options.with_source(source))
     } else {
-        // カンマ区切りの文字列に変換
-        let output = video_data
-            .frames
-            .iter()
-            .map(|img| generate_braille(img, cols, rows))
-            .collect::<Vec<_>>()
-            .join(",\n");
-        // スクリプト出力
-        let wait_sec = (1.0 / video_data.fps) as f32;
-        if let Ok(script) = scriptify::generate_bash_script_for_video(&output, wait_sec) {
-            scriptify::save_script(&script, &args.scriptify)?;
-            eprintln!("Script file is created: {}", args.scriptify);
+        Ok(options)
+    }
+}
+
+/// すべてのフレームを pts 付きのBraille文字列にする
+fn render_text_frames<S: FrameSource>(
+    frames: S,
+    frame_renderer: &FrameRenderer,
+) -> Result<Vec<export::TextFrame>> {
+    frames
+        .map(|frame| {
+            let frame = frame?;
+            Ok(export::TextFrame {
+                pts: frame.pts,
+                text: frame_renderer.render(&frame)?,
+            })
+        })
+        .collect()
+}
+
+/// 動画・アニメーションのフレームを、指定に応じてファイル出力・スクリプト出力・再生する
+fn render_frames<S: FrameSource>(
+    mut frames: S,
+    fps: f32,
+    cols: u32,
+    rows: u32,
+    args: &args::Args,
+    script_options: &ScriptOptions,
+) -> Result<()> {
+    // 字幕の時刻は入力の時刻なので、切り出し・速度変更後のフレームの時刻に合わせる
+    let subtitles = if args.subtitles.is_empty() {
+        None
+    } else {
+        Some(Subtitles::load(&args.subtitles)?.retime(&Timeline::new(args)?))
+    };
+    let frame_renderer = FrameRenderer::new(cols, rows, subtitles);
+    if !args.output.is_empty() {
+        // ファイル出力
+        let format = export::OutputFormat::resolve(args.format, &args.output)?;
+        let frames = render_text_frames(frames, &frame_renderer)?;
+        export::save_video_output(&frames, fps, &args.output, format, script_options)?;
+        eprintln!("Output file is created: {}", args.output);
+    } else if args.scriptify.is_empty() {
+        // フレームはデコードされ次第届くので、最初のフレームから再生を始める
+        // 描画が遅れた場合はフレームを飛ばして実時間に合わせる
+        let stats = if args.interactive {
+            player::play_interactive(&mut frames, fps, &frame_renderer)?
         } else {
-            eprintln!("Failed to generate script");
+            player::play(frames, fps, &frame_renderer)?
+        };
+        if args.verbose {
+            eprintln!(
+                "Shown frames: {}, Dropped frames: {}",
+                stats.shown, stats.dropped
+            );
         }
+    } else {
+        // スクリプト出力
+        let frames = render_text_frames(frames, &frame_renderer)?;
+        let script = scriptify::generate_script_for_video(&frames, fps, script_options)?;
+        scriptify::save_script(&script, &args.scriptify)?;
+        eprintln!("Script file is created: {}", args.scriptify);
     }
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> ExitCode {
     let args = args::Args::parse();
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run(args: args::Args) -> Result<()> {
+    // サブコマンド
+    match &args.command {
+        Some(args::Command::Montage(montage_args)) => return montage::run_montage(montage_args),
+        Some(args::Command::Thumbnails(thumbnails_args)) => {
+            return thumbnails::run_thumbnails(thumbnails_args);
+        }
+        Some(args::Command::Inspect(inspect_args)) => return inspect::run_inspect(inspect_args),
+        None => {}
+    }
+    // 複数入力はバッチモードで処理
+    if batch::is_batch(&args) {
+        return batch::run_batch(&args, &command_line());
+    }
     // 入力画像ファイルパスとサイズ
-    let img_path = args.input.clone();
+    let img_path = args.input[0].clone();
+    // 動画ファイルは全体を読み込まず、パスのまま処理する
+    if !input::is_stdin(&img_path) && file_type::infer_type(&img_path) == file_type::FileType::Video
+    {
+        return process_video(file_type::FileType::Video, &img_path, None, args);
+    }
+    // 入力データを読み込み ("-" の場合は標準入力)
+    let data = input::read_input(&img_path)?;
     // ファイル種別を判定
-    let ftype = file_type::infer_type(&img_path);
+    let ftype = file_type::infer_type_from_bytes(&data);
     if ftype == file_type::FileType::Image {
-        let img: GrayImage = measure_time!(image::open(img_path.clone())?.to_luma8());
+        let img: GrayImage = measure_time!(image::load_from_memory(&data)?.to_luma8());
 
         let (w, h) = img.dimensions();
         let ratio = w as f32 / h as f32 * 2f32;
-        let (mut _cols, mut _rows) = (args.size.0, args.size.1);
+        let (mut _cols, mut _rows) = (args.render.size.0, args.render.size.1);
         let (cols, rows) = convert_size(w, h, _cols, _rows);
 
         if args.verbose {
@@ -97,37 +250,49 @@ fn main() -> Result<(), Box<dyn Error>> {
             eprintln!("File type: {:?}", ftype);
             eprintln!("Image size: {}x{}", w, h);
             eprintln!("Ratio: {}", ratio);
-            eprintln!("Project Size: {:?}", args.size);
+            eprintln!("Project Size: {:?}", args.render.size);
             eprintln!("Cols: {}, Rows: {}", cols, rows);
         }
-        let img = image_processing::process_image(
+        let pre = image_processing::process_image(
             &img,
             cols,
             rows,
-            args.contrast,
-            args.invert,
-            args.binarize,
-        );
+            args.render.contrast,
+            args.render.invert,
+            args::BinarizeOption::None,
+        )?;
+        let img = image_processing::binarize(&pre, args.render.binarize);
+        if let Some(mut dumper) = dump::FrameDumper::new(&args.dump_frames, args.dump_every)? {
+            dumper.dump(&pre, &img)?;
+        }
 
-        let output = measure_time!(generate_braille(&img, cols, rows));
+        let output = measure_time!(generate_braille(&img, cols, rows))?;
+        let script_options = script_options(&args, &img_path, Some(&data))?;
 
-        if args.scriptify.is_empty() {
+        if !args.output.is_empty() {
+            // ファイル出力
+            let format = export::OutputFormat::resolve(args.format, &args.output)?;
+            export::save_image_output(&output, &args.output, format, &script_options)?;
+            eprintln!("Output file is created: {}", args.output);
+        } else if args.scriptify.is_empty() {
             println!("{}", output);
         } else {
             // スクリプト出力
-            if let Ok(script) = scriptify::generate_bash_script_for_image(&output) {
-                scriptify::save_script(&script, &args.scriptify)?;
-                eprintln!("Script file is created: {}", args.scriptify);
-            } else {
-                eprintln!("Failed to generate script");
-            }
+            let script = scriptify::generate_script_for_image(&output, &script_options)?;
+            scriptify::save_script(&script, &args.scriptify)?;
+            eprintln!("Script file is created: {}", args.scriptify);
         }
+    } else if ftype == file_type::FileType::Animation {
+        // アニメーションの処理
+        process_animation(ftype, &img_path, &data, &args)?;
     } else if ftype == file_type::FileType::Video {
         // 動画処理
-        process_video(ftype, &img_path, args);
+        process_video(ftype, &img_path, Some(&data), args)?;
     } else {
-        eprintln!("Unsupported file type: {:?}", ftype);
-        return Ok(());
+        return Err(BraillerError::UnsupportedFormat(format!(
+            "{} is neither an image nor a video",
+            img_path
+        )));
     }
 
     Ok(())
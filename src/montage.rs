@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use crate::args::MontageArgs;
+use crate::error::Result;
+use crate::image_processing::render_braille;
+use crate::input;
+use crate::terminal::{char_width, display_width};
+
+/// 空のBraille文字。タイルの余白を埋めるのに使う
+const BLANK: char = '\u{2800}';
+
+/// モンタージュの1タイル
+pub struct Tile {
+    pub text: String,
+    pub caption: String,
+}
+
+/// 文字列を表示幅 width に切り詰め、足りない分を fill (幅1の文字) で埋める
+fn fit(line: &str, width: usize, fill: char) -> String {
+    let mut used = 0;
+    let mut fitted: String = line
+        .chars()
+        .take_while(|&c| {
+            used += char_width(c);
+            used <= width
+        })
+        .collect();
+    let len = display_width(&fitted);
+    fitted.extend(std::iter::repeat_n(fill, width - len));
+    fitted
+}
+
+/// タイルを columns 列のグリッドに並べる。columns が 0 の場合は1行に並べる。
+/// タイルの大きさは最大のタイルに合わせ、captions が true の場合は各タイルの下にキャプションを表示する。
+pub fn layout(tiles: &[Tile], columns: usize, gap: usize, captions: bool) -> String {
+    let columns = if columns == 0 { tiles.len() } else { columns }.max(1);
+    let width = tiles
+        .iter()
+        .flat_map(|tile| tile.text.lines())
+        .map(display_width)
+        .max()
+        .unwrap_or(0);
+    let separator = " ".repeat(gap);
+    let mut output = String::new();
+
+    for (i, row) in tiles.chunks(columns).enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        let height = row
+            .iter()
+            .map(|tile| tile.text.lines().count())
+            .max()
+            .unwrap_or(0);
+        for y in 0..height {
+            let line = row
+                .iter()
+                .map(|tile| fit(tile.text.lines().nth(y).unwrap_or(""), width, BLANK))
+                .collect::<Vec<_>>()
+                .join(&separator);
+            output.push_str(&line);
+            output.push('\n');
+        }
+        if captions {
+            let line = row
+                .iter()
+                .map(|tile| fit(&tile.caption, width, ' '))
+                .collect::<Vec<_>>()
+                .join(&separator);
+            output.push_str(line.trim_end());
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// 入力ファイル名とオプション文字列からキャプションを作る
+fn caption(input: &str, variant: Option<&str>, show_input: bool) -> String {
+    let name = Path::new(input)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| input.to_string());
+    match variant {
+        Some(variant) if show_input => format!("{} {}", name, variant),
+        Some(variant) => variant.to_string(),
+        None => name,
+    }
+}
+
+/// 各入力を各オプションセットで描画し、グリッド状に並べて出力する
+pub fn run_montage(args: &MontageArgs) -> Result<()> {
+    let variants: Vec<Option<&str>> = if args.variants.is_empty() {
+        vec![None]
+    } else {
+        args.variants.iter().map(|v| Some(v.as_str())).collect()
+    };
+    let show_input = args.input.len() > 1;
+
+    let mut tiles = Vec::new();
+    for input in &args.input {
+        let data = input::read_input(input)?;
+        let img = image::load_from_memory(&data)?.to_luma8();
+        for variant in &variants {
+            let opts = match variant {
+                Some(variant) => args.render.with_variant(variant)?,
+                None => args.render.clone(),
+            };
+            tiles.push(Tile {
+                text: render_braille(&img, &opts)?,
+                caption: caption(input, *variant, show_input),
+            });
+        }
+    }
+
+    print!("{}", layout(&tiles, args.columns, args.gap, args.captions));
+    Ok(())
+}
@@ -0,0 +1,424 @@
+use image::GrayImage;
+use std::io::{self, Write};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::braille::generate_braille;
+use crate::error::Result;
+use crate::measure_time;
+use crate::subtitles::Subtitles;
+use crate::terminal::{DiffRenderer, Key, RawTerminal, supports_synchronized_output};
+use crate::timeline::frame_interval;
+
+/// 対話モードの矢印キーでシークする量
+const SEEK_STEP: Duration = Duration::from_secs(5);
+/// 対話モードの +/- キーで変更する再生速度の刻みと範囲
+const SPEED_STEP: f64 = 0.25;
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 4.0;
+
+/// 再生する1フレーム。pts は先頭フレームからの表示時刻。
+pub struct Frame {
+    pub image: GrayImage,
+    pub pts: Duration,
+}
+
+/// シークできるフレーム列。対話モードの再生に使う。
+pub trait FrameSource: Iterator<Item = Result<Frame>> {
+    /// pts の位置から再生し直す。以降のフレームは pts 以降のものになる。
+    fn seek(&mut self, pts: Duration) -> Result<()>;
+    /// 全体の長さ。分からない場合は None。
+    fn duration(&self) -> Option<Duration>;
+}
+
+/// フレームの表示タイミングの判定結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameAction {
+    /// 表示時刻まで待ってから表示する
+    Show,
+    /// 次のフレームの表示時刻を過ぎているので表示せずに捨てる
+    Drop,
+}
+
+/// 再生位置を実時間と再生速度から求め、各フレームを pts どおりに表示するための時計
+pub struct PlaybackClock {
+    /// 再生位置の基準とした時刻
+    anchor: Instant,
+    /// anchor 時点の再生位置
+    anchor_pts: Duration,
+    speed: f64,
+    paused: bool,
+    frame_interval: Duration,
+}
+
+/// 時刻を受け取る *_at のメソッドは、現在時刻の代わりに now を使う (テスト用)
+impl PlaybackClock {
+    /// 現在時刻を first_pts のフレームの表示時刻として時計を開始する
+    pub fn new(fps: f32, first_pts: Duration) -> Result<Self> {
+        Self::new_at(fps, first_pts, Instant::now())
+    }
+
+    pub fn new_at(fps: f32, first_pts: Duration, now: Instant) -> Result<Self> {
+        Ok(Self {
+            anchor: now,
+            anchor_pts: first_pts,
+            speed: 1.0,
+            paused: false,
+            frame_interval: frame_interval(fps)?,
+        })
+    }
+
+    /// 現在の再生位置
+    pub fn position(&self) -> Duration {
+        self.position_at(Instant::now())
+    }
+
+    pub fn position_at(&self, now: Instant) -> Duration {
+        if self.paused {
+            self.anchor_pts
+        } else {
+            self.anchor_pts
+                + now
+                    .saturating_duration_since(self.anchor)
+                    .mul_f64(self.speed)
+        }
+    }
+
+    /// 現在の再生位置を基準にし直す。速度の変更や一時停止の前に呼ぶ。
+    fn reanchor(&mut self, now: Instant) {
+        self.anchor_pts = self.position_at(now);
+        self.anchor = now;
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.set_speed_at(speed, Instant::now());
+    }
+
+    pub fn set_speed_at(&mut self, speed: f64, now: Instant) {
+        self.reanchor(now);
+        self.speed = speed;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.set_paused_at(paused, Instant::now());
+    }
+
+    pub fn set_paused_at(&mut self, paused: bool, now: Instant) {
+        self.reanchor(now);
+        self.paused = paused;
+    }
+
+    /// 再生位置を pts に移動する
+    pub fn seek(&mut self, pts: Duration) {
+        self.seek_at(pts, Instant::now());
+    }
+
+    pub fn seek_at(&mut self, pts: Duration, now: Instant) {
+        self.anchor = now;
+        self.anchor_pts = pts;
+    }
+
+    /// pts のフレームを表示するかを判定する。
+    /// 描画が遅れて次のフレームの表示時刻も過ぎている場合は Drop を返す。
+    pub fn schedule(&self, pts: Duration) -> FrameAction {
+        self.schedule_at(pts, Instant::now())
+    }
+
+    pub fn schedule_at(&self, pts: Duration, now: Instant) -> FrameAction {
+        if self.position_at(now) >= pts + self.frame_interval {
+            FrameAction::Drop
+        } else {
+            FrameAction::Show
+        }
+    }
+
+    /// pts の表示時刻までの実時間
+    pub fn time_until(&self, pts: Duration) -> Duration {
+        self.time_until_at(pts, Instant::now())
+    }
+
+    pub fn time_until_at(&self, pts: Duration, now: Instant) -> Duration {
+        pts.saturating_sub(self.position_at(now))
+            .div_f64(self.speed)
+    }
+
+    /// pts の表示時刻まで待つ
+    pub fn wait_until(&self, pts: Duration) {
+        let wait = self.time_until(pts);
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// フレームを点字の文字列にする。字幕が指定されている場合はフレームの下に表示する。
+pub struct FrameRenderer {
+    cols: u32,
+    rows: u32,
+    subtitles: Option<Subtitles>,
+}
+
+impl FrameRenderer {
+    pub fn new(cols: u32, rows: u32, subtitles: Option<Subtitles>) -> Self {
+        Self {
+            cols,
+            rows,
+            subtitles,
+        }
+    }
+
+    /// 字幕を含めた表示の行数
+    pub fn height(&self) -> u32 {
+        self.rows + self.subtitles.as_ref().map_or(0, |s| s.lines() as u32)
+    }
+
+    pub fn render(&self, frame: &Frame) -> Result<String> {
+        let mut output = measure_time!(generate_braille(&frame.image, self.cols, self.rows))?;
+        if let Some(subtitles) = &self.subtitles {
+            output.push_str(&subtitles.render_area(frame.pts, self.cols as usize));
+        }
+        Ok(output)
+    }
+}
+
+/// 再生結果の統計
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PlaybackStats {
+    pub shown: usize,
+    pub dropped: usize,
+}
+
+/// フレームを pts に合わせて端末に表示する。描画が追いつかない場合はフレームを飛ばす。
+/// 前フレームから変更されたセルのみを書き換える。
+pub fn play<I>(frames: I, fps: f32, frame_renderer: &FrameRenderer) -> Result<PlaybackStats>
+where
+    I: Iterator<Item = Result<Frame>>,
+{
+    let mut stats = PlaybackStats::default();
+    let mut stdout = io::stdout().lock();
+    let mut clock = None;
+    let mut renderer = DiffRenderer::new(supports_synchronized_output());
+    for frame in frames {
+        let frame = frame?;
+        // 最初のフレームが届いた時点から時計を進める
+        let clock = match clock {
+            Some(ref mut clock) => clock,
+            None => clock.insert(PlaybackClock::new(fps, frame.pts)?),
+        };
+        match clock.schedule(frame.pts) {
+            FrameAction::Drop => {
+                stats.dropped += 1;
+                continue;
+            }
+            FrameAction::Show => {
+                let output = frame_renderer.render(&frame)?;
+                clock.wait_until(frame.pts);
+                write!(stdout, "{}", renderer.render(&output))?;
+                stdout.flush()?;
+                stats.shown += 1;
+            }
+        }
+    }
+    Ok(stats)
+}
+
+/// 対話モードでのキー操作の結果
+enum Control {
+    Continue,
+    /// シークしたので現在のフレームを捨てる
+    Seeked,
+    Quit,
+}
+
+/// 対話モードの再生状態
+struct InteractivePlayer {
+    clock: PlaybackClock,
+    /// 開始・シークの後、最初のフレームが届いた時点で時計をそのフレームに合わせる
+    awaiting_frame: bool,
+    duration: Option<Duration>,
+    looping: bool,
+    /// 表示フレームレートの計測用 (計測開始時刻, 表示フレーム数)
+    fps_window: (Instant, u32),
+    measured_fps: f32,
+    /// ステータス行を表示する行 (1始まり)
+    status_row: u32,
+}
+
+impl InteractivePlayer {
+    fn handle_key<S: FrameSource>(&mut self, key: Key, source: &mut S) -> Result<Control> {
+        match key {
+            Key::Char('q') | Key::Char('Q') | Key::Interrupt => return Ok(Control::Quit),
+            Key::Space => self.clock.set_paused(!self.clock.is_paused()),
+            Key::Char('l') | Key::Char('L') => self.looping = !self.looping,
+            Key::Plus => {
+                let speed = (self.clock.speed() + SPEED_STEP).min(MAX_SPEED);
+                self.clock.set_speed(speed);
+            }
+            Key::Minus => {
+                let speed = (self.clock.speed() - SPEED_STEP).max(MIN_SPEED);
+                self.clock.set_speed(speed);
+            }
+            Key::Left | Key::Right => {
+                let position = self.clock.position();
+                let mut target = if key == Key::Right {
+                    position + SEEK_STEP
+                } else {
+                    position.saturating_sub(SEEK_STEP)
+                };
+                if let Some(duration) = self.duration {
+                    target = target.min(duration);
+                }
+                source.seek(target)?;
+                self.clock.seek(target);
+                self.awaiting_frame = true;
+                return Ok(Control::Seeked);
+            }
+            _ => {}
+        }
+        Ok(Control::Continue)
+    }
+
+    /// 表示フレーム数から実際の表示フレームレートを更新する
+    fn count_frame(&mut self) {
+        let (start, count) = &mut self.fps_window;
+        *count += 1;
+        let elapsed = start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.measured_fps = *count as f32 / elapsed.as_secs_f32();
+            *start = Instant::now();
+            *count = 0;
+        }
+    }
+
+    /// フレームの下に表示するステータス行
+    fn status_line(&self) -> String {
+        let duration = self
+            .duration
+            .map(format_time)
+            .unwrap_or_else(|| "--:--.-".to_string());
+        format!(
+            "\x1B[{};1H\x1B[2K{} {} / {}  {:.2}x  {:.1} fps{}  [space] pause [<-/->] seek [+/-] speed [l] loop [q] quit",
+            self.status_row,
+            if self.clock.is_paused() { "||" } else { "> " },
+            format_time(self.clock.position()),
+            duration,
+            self.clock.speed(),
+            self.measured_fps,
+            if self.looping { "  loop" } else { "" },
+        )
+    }
+}
+
+/// 再生位置を mm:ss.s の形式にする
+fn format_time(time: Duration) -> String {
+    let secs = time.as_secs_f64();
+    format!("{:02}:{:04.1}", (secs / 60.0) as u64, secs % 60.0)
+}
+
+/// キーボードで操作しながらフレームを再生する。
+/// space: 一時停止, ←/→: 5秒シーク, +/-: 速度変更, l: ループ切り替え, q / Ctrl-C: 終了
+pub fn play_interactive<S: FrameSource>(
+    source: &mut S,
+    fps: f32,
+    frame_renderer: &FrameRenderer,
+) -> Result<PlaybackStats> {
+    // 終了時 (エラーやパニックを含む) に Drop で端末の状態が戻る
+    let terminal = RawTerminal::enable()?;
+    let keys = terminal.keys()?;
+
+    let mut stats = PlaybackStats::default();
+    let mut stdout = io::stdout().lock();
+    let mut renderer = DiffRenderer::new(supports_synchronized_output());
+    let mut player = InteractivePlayer {
+        clock: PlaybackClock::new(fps, Duration::ZERO)?,
+        awaiting_frame: true,
+        duration: source.duration(),
+        looping: false,
+        fps_window: (Instant::now(), 0),
+        measured_fps: 0.0,
+        status_row: frame_renderer.height() + 1,
+    };
+
+    // 先頭に戻してからまだフレームが届いていない
+    let mut rewound = false;
+    'frames: loop {
+        let frame = match source.next() {
+            Some(frame) => frame?,
+            // 先頭に戻しても1フレームもない場合は終了する
+            None if player.looping && !rewound => {
+                source.seek(Duration::ZERO)?;
+                player.clock.seek(Duration::ZERO);
+                player.awaiting_frame = true;
+                rewound = true;
+                continue;
+            }
+            None => break,
+        };
+        rewound = false;
+        // デコードを待った時間の分だけフレームが遅れて捨てられないように、届いた時点から時計を進める
+        if player.awaiting_frame {
+            player.clock.seek(frame.pts);
+            player.awaiting_frame = false;
+        }
+        if player.clock.schedule(frame.pts) == FrameAction::Drop {
+            stats.dropped += 1;
+            continue;
+        }
+        let output = frame_renderer.render(&frame)?;
+        let mut shown = false;
+
+        // 表示時刻までキー入力を待つ
+        loop {
+            // 一時停止中は現在のフレームを表示してからキー入力を待つ
+            if player.clock.is_paused() && !shown {
+                write!(stdout, "{}", renderer.render(&output))?;
+                shown = true;
+            }
+            write!(stdout, "{}", player.status_line())?;
+            stdout.flush()?;
+
+            let key = if player.clock.is_paused() {
+                match keys.recv() {
+                    Ok(key) => key,
+                    // 端末からの入力が途絶えた場合は終了する
+                    Err(_) => break 'frames,
+                }
+            } else {
+                match keys.recv_timeout(player.clock.time_until(frame.pts)) {
+                    Ok(key) => key,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        player.clock.wait_until(frame.pts);
+                        break;
+                    }
+                }
+            };
+            match player.handle_key(key, source)? {
+                Control::Continue => {}
+                Control::Seeked => continue 'frames,
+                Control::Quit => break 'frames,
+            }
+        }
+
+        if !shown {
+            write!(stdout, "{}", renderer.render(&output))?;
+        }
+        player.count_frame();
+        write!(stdout, "{}", player.status_line())?;
+        stdout.flush()?;
+        stats.shown += 1;
+    }
+
+    // ステータス行の下にカーソルを移動して終了する
+    writeln!(stdout)?;
+    Ok(stats)
+}
@@ -1,26 +1,449 @@
 use base64::{Engine as _, engine::general_purpose};
+use clap::ValueEnum;
 use flate2::Compression;
-use flate2::write::GzEncoder;
+use flate2::write::{GzEncoder, ZlibEncoder};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
 use std::{
+    fs,
     io::{self, Write},
+    ops::RangeInclusive,
     os::unix::fs::OpenOptionsExt,
+    path::Path,
+    time::Duration,
 };
+use xz2::write::XzEncoder;
 
-fn gzip_and_base64_encode(input: &str) -> io::Result<String> {
-    // Gzip 圧縮
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-    encoder.write_all(input.as_bytes())?;
-    let compressed_data = encoder.finish()?; // Gzip 圧縮結果を取得
+use crate::args::Args;
+use crate::error::{BraillerError, Result};
+use crate::export::TextFrame;
+use crate::input;
+use crate::terminal::{char_width, same_shape, write_changed_runs};
+use crate::timeline::frame_interval;
 
-    // Base64 エンコード
-    let encoded = general_purpose::STANDARD.encode(compressed_data);
-    Ok(encoded)
+/// 生成するスクリプトのシェル (または Python / Node.js のプレーヤー)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptShell {
+    // POSIX sh (dash, BusyBox ash など)
+    Sh,
+    Bash,
+    Fish,
+    Powershell,
+    // bash のない Windows / macOS でも実行できる単体のプレーヤー
+    Python,
+    Node,
 }
 
-pub static BASH_TEMPLATE_FOR_IMAGE: &str = r#"#!/bin/bash
+impl ScriptShell {
+    /// 出力ファイルの拡張子から判定する。判定できない場合は bash
+    pub fn from_path(path: &str) -> Self {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "fish" => ScriptShell::Fish,
+            "ps1" => ScriptShell::Powershell,
+            "py" => ScriptShell::Python,
+            "js" | "cjs" => ScriptShell::Node,
+            _ => ScriptShell::Bash,
+        }
+    }
+}
 
-# BRAILLE_TEXT START
-BRAILLE_TEXT=$(cat <<'EOF' | base64 -d | gzip -d
+/// スクリプトに埋め込むテキストの圧縮形式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptCompression {
+    None,
+    // Python / Node.js のプレーヤーでは zlib 形式になる
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl ScriptCompression {
+    /// 指定できる圧縮レベルの範囲と既定のレベル。圧縮しない場合は None
+    fn levels(self) -> Option<(RangeInclusive<u32>, u32)> {
+        match self {
+            ScriptCompression::None => None,
+            ScriptCompression::Gzip => Some((0..=9, 6)),
+            ScriptCompression::Xz => Some((0..=9, 6)),
+            ScriptCompression::Zstd => Some((1..=22, 3)),
+        }
+    }
+
+    /// 展開に使うコマンド
+    fn command(self) -> Option<&'static str> {
+        match self {
+            ScriptCompression::None => None,
+            ScriptCompression::Gzip => Some("gzip"),
+            ScriptCompression::Xz => Some("xz"),
+            ScriptCompression::Zstd => Some("zstd"),
+        }
+    }
+}
+
+/// スクリプトの元になった入力。スクリプト先頭のメタデータに書き出す
+#[derive(Debug, Clone)]
+pub struct ScriptSource {
+    /// 入力のファイル名 (標準入力の場合は "-")
+    pub name: String,
+    /// 入力データの SHA-256 (16進数)
+    pub sha256: String,
+    /// brailler を実行したときの引数
+    pub options: String,
+}
+
+impl ScriptSource {
+    /// path から読み込んだ data の情報。options は呼び出し元が渡すコマンドライン引数
+    pub fn new(path: &str, data: &[u8], options: &str) -> Self {
+        Self::from_digest(path, Sha256::digest(data).as_slice(), options)
+    }
+
+    /// 動画など、メモリに読み込んでいないファイルの情報。ファイルは少しずつ読んでハッシュを計算する
+    pub fn from_file(path: &str, options: &str) -> Result<Self> {
+        let mut file = fs::File::open(path).map_err(|e| BraillerError::io(path, e))?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher).map_err(|e| BraillerError::io(path, e))?;
+        Ok(Self::from_digest(
+            path,
+            hasher.finalize().as_slice(),
+            options,
+        ))
+    }
+
+    fn from_digest(path: &str, digest: &[u8], options: &str) -> Self {
+        let name = if input::is_stdin(path) {
+            path.to_string()
+        } else {
+            Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.to_string())
+        };
+        let sha256 = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        Self {
+            name,
+            sha256,
+            options: options.to_string(),
+        }
+    }
+}
+
+/// スクリプト出力の設定
+#[derive(Debug, Clone)]
+pub struct ScriptOptions {
+    pub shell: ScriptShell,
+    /// 動画の各フレームを前フレームからの差分で保存する場合の、全体を保存するフレームの間隔。
+    /// None の場合はすべてのフレームの全体を保存する。
+    pub keyframe_interval: Option<usize>,
+    /// 動画の再生回数。0 の場合は Ctrl-C まで繰り返す
+    pub loops: u32,
+    /// 動画の再生中はカーソルを隠す
+    pub hide_cursor: bool,
+    /// 動画を代替スクリーンで再生し、終了時に元の画面に戻す
+    pub alt_screen: bool,
+    pub compression: ScriptCompression,
+    /// 圧縮レベル。None の場合は各形式の既定のレベル
+    pub compression_level: Option<u32>,
+    /// 入力の情報。None の場合はメタデータに含めない
+    pub source: Option<ScriptSource>,
+}
+
+impl ScriptOptions {
+    pub fn new(args: &Args) -> Self {
+        // --script-shell を省略した場合は出力ファイルの拡張子から判定する
+        let path = if args.scriptify.is_empty() {
+            &args.output
+        } else {
+            &args.scriptify
+        };
+        Self {
+            shell: args
+                .script_shell
+                .unwrap_or_else(|| ScriptShell::from_path(path)),
+            keyframe_interval: args.script_delta.then_some(args.keyframe_interval as usize),
+            loops: args.script_loop,
+            hide_cursor: args.script_hide_cursor,
+            alt_screen: args.script_alt_screen,
+            compression: args.script_compression,
+            compression_level: args.script_compression_level,
+            source: None,
+        }
+    }
+
+    /// メタデータに入力の情報を含める
+    pub fn with_source(mut self, source: ScriptSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// 動画の再生開始時に出力するエスケープシーケンスと、終了時に端末を戻すシーケンス (ESC を除いた部分)
+    fn terminal_sequences(&self) -> (Vec<&'static str>, Vec<&'static str>) {
+        let mut setup = Vec::new();
+        let mut restore = Vec::new();
+        if self.alt_screen {
+            setup.push("[?1049h");
+            restore.push("[?1049l");
+        }
+        if self.hide_cursor {
+            setup.push("[?25l");
+            restore.insert(0, "[?25h");
+        }
+        (setup, restore)
+    }
+}
+
+impl Default for ScriptOptions {
+    fn default() -> Self {
+        Self {
+            shell: ScriptShell::Bash,
+            keyframe_interval: None,
+            loops: 1,
+            hide_cursor: false,
+            alt_screen: false,
+            compression: ScriptCompression::Gzip,
+            compression_level: None,
+            source: None,
+        }
+    }
+}
+
+/// ESC に続くシーケンスの並びを、テンプレートに埋め込むシェルごとの文字列にする。
+/// sh / bash / PowerShell / Python / JavaScript は二重引用符の中身、fish は引用符を含む引数になる。
+fn shell_escapes(shell: ScriptShell, sequences: &[&str]) -> String {
+    match shell {
+        ScriptShell::Sh | ScriptShell::Bash => sequences
+            .iter()
+            .map(|sequence| format!("${{ESC}}{}", sequence))
+            .collect(),
+        ScriptShell::Powershell => sequences
+            .iter()
+            .map(|sequence| format!("${{esc}}{}", sequence))
+            .collect(),
+        ScriptShell::Python | ScriptShell::Node => sequences
+            .iter()
+            .map(|sequence| format!("\\x1b{}", sequence))
+            .collect(),
+        ScriptShell::Fish if sequences.is_empty() => "''".to_string(),
+        ScriptShell::Fish => sequences
+            .iter()
+            .map(|sequence| format!("\\e'{}'", sequence))
+            .collect(),
+    }
+}
+
+/// input を options.compression で圧縮する。Python / Node.js の gzip は zlib 形式にする。
+/// 圧縮しない場合、圧縮レベルは無視する
+fn compress(input: &str, options: &ScriptOptions) -> Result<Vec<u8>> {
+    let level = match options.compression.levels() {
+        Some((range, default_level)) => {
+            let level = options.compression_level.unwrap_or(default_level);
+            if !range.contains(&level) {
+                return Err(BraillerError::InvalidArgument(format!(
+                    "compression level {} is out of range for {} ({}-{})",
+                    level,
+                    options.compression.command().unwrap_or_default(),
+                    range.start(),
+                    range.end()
+                )));
+            }
+            level
+        }
+        None => 0,
+    };
+    let compressed = match (options.compression, options.shell) {
+        (ScriptCompression::None, _) => input.as_bytes().to_vec(),
+        (ScriptCompression::Gzip, ScriptShell::Python | ScriptShell::Node) => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(level));
+            encoder.write_all(input.as_bytes())?;
+            encoder.finish()?
+        }
+        (ScriptCompression::Gzip, _) => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level));
+            encoder.write_all(input.as_bytes())?;
+            encoder.finish()?
+        }
+        (ScriptCompression::Xz, _) => {
+            let mut encoder = XzEncoder::new(Vec::new(), level);
+            encoder.write_all(input.as_bytes())?;
+            encoder.finish()?
+        }
+        (ScriptCompression::Zstd, _) => zstd::encode_all(input.as_bytes(), level as i32)?,
+    };
+    Ok(compressed)
+}
+
+/// 展開コマンドがない場合に表示するメッセージ
+fn missing_command_message(command: &str) -> String {
+    format!(
+        "This script needs {} to decompress the braille text. Install {} and run it again.",
+        command, command
+    )
+}
+
+/// テンプレートの {{CHECK}} (展開コマンドの確認) と {{DECOMPRESS}} (展開処理) の中身
+fn decompress_snippets(shell: ScriptShell, compression: ScriptCompression) -> (String, String) {
+    let command = compression.command();
+    let message = command.map(missing_command_message).unwrap_or_default();
+    let pipe = match command {
+        Some(command) => format!("{} -dc", command),
+        None => "cat".to_string(),
+    };
+    match (shell, command) {
+        (ScriptShell::Sh | ScriptShell::Bash, Some(command)) => (
+            format!(
+                "if ! command -v {} >/dev/null 2>&1; then\n    echo \"{}\" >&2\n    exit 1\nfi\n\n",
+                command, message
+            ),
+            pipe,
+        ),
+        (ScriptShell::Fish, Some(command)) => (
+            format!(
+                "if not command -q {}\n    echo \"{}\" >&2\n    exit 1\nend\n\n",
+                command, message
+            ),
+            pipe,
+        ),
+        (ScriptShell::Sh | ScriptShell::Bash | ScriptShell::Fish, None) => (String::new(), pipe),
+        (ScriptShell::Powershell, _) => (String::new(), powershell_decompress(compression)),
+        (ScriptShell::Python, _) => (String::new(), python_decompress(compression)),
+        (ScriptShell::Node, _) => (String::new(), node_decompress(compression)),
+    }
+}
+
+/// $bytes を展開して $text にする PowerShell のコード。
+/// .NET は xz と zstd に対応していないため、コマンドで展開する
+fn powershell_decompress(compression: ScriptCompression) -> String {
+    match compression {
+        ScriptCompression::None => "$text = [System.Text.Encoding]::UTF8.GetString($bytes)".to_string(),
+        ScriptCompression::Gzip => "$stream = New-Object System.IO.Compression.GZipStream((New-Object System.IO.MemoryStream(, $bytes)), [System.IO.Compression.CompressionMode]::Decompress)
+$text = (New-Object System.IO.StreamReader($stream, [System.Text.Encoding]::UTF8)).ReadToEnd()".to_string(),
+        ScriptCompression::Xz | ScriptCompression::Zstd => {
+            let command = compression.command().unwrap_or_default();
+            POWERSHELL_COMMAND_DECOMPRESS
+                .replace("{{COMMAND}}", command)
+                .replace("{{MESSAGE}}", &missing_command_message(command))
+        }
+    }
+}
+
+static POWERSHELL_COMMAND_DECOMPRESS: &str = r#"$decompressor = Get-Command {{COMMAND}} -CommandType Application -ErrorAction SilentlyContinue | Select-Object -First 1
+if (-not $decompressor) {
+    [Console]::Error.WriteLine("{{MESSAGE}}")
+    exit 1
+}
+$process = New-Object System.Diagnostics.Process
+$process.StartInfo.FileName = $decompressor.Source
+$process.StartInfo.Arguments = '-dc'
+$process.StartInfo.UseShellExecute = $false
+$process.StartInfo.RedirectStandardInput = $true
+$process.StartInfo.RedirectStandardOutput = $true
+$process.StartInfo.StandardOutputEncoding = [System.Text.Encoding]::UTF8
+[void]$process.Start()
+$reader = $process.StandardOutput.ReadToEndAsync()
+$process.StandardInput.BaseStream.Write($bytes, 0, $bytes.Length)
+$process.StandardInput.Close()
+$text = $reader.Result
+$process.WaitForExit()"#;
+
+/// bytes を展開する Python の decompress 関数。
+/// 標準ライブラリで展開できない場合はコマンドで展開する
+fn python_decompress(compression: ScriptCompression) -> String {
+    let body = match compression {
+        ScriptCompression::None => "    return data".to_string(),
+        ScriptCompression::Gzip => {
+            "    import zlib\n\n    return zlib.decompress(data)".to_string()
+        }
+        ScriptCompression::Xz => "    try:
+        import lzma
+    except ImportError:
+        return run_decompressor(\"xz\", data)
+    return lzma.decompress(data)"
+            .to_string(),
+        ScriptCompression::Zstd => "    try:
+        from compression import zstd  # Python 3.14+
+    except ImportError:
+        return run_decompressor(\"zstd\", data)
+    return zstd.decompress(data)"
+            .to_string(),
+    };
+    let mut code = format!("def decompress(data):\n{}", body);
+    if let (ScriptCompression::Xz | ScriptCompression::Zstd, Some(command)) =
+        (compression, compression.command())
+    {
+        code.push_str(&PYTHON_RUN_DECOMPRESSOR.replace(
+            "{{MESSAGE}}",
+            &missing_command_message(command).replace(command, "{command}"),
+        ));
+    }
+    code
+}
+
+static PYTHON_RUN_DECOMPRESSOR: &str = r#"
+
+
+def run_decompressor(command, data):
+    import shutil
+    import subprocess
+
+    if shutil.which(command) is None:
+        sys.exit(f"{{MESSAGE}}")
+    return subprocess.run([command, "-dc"], input=data, stdout=subprocess.PIPE, check=True).stdout"#;
+
+/// Buffer を展開する JavaScript の decompress 関数。
+/// Node.js で展開できない場合はコマンドで展開する
+fn node_decompress(compression: ScriptCompression) -> String {
+    let body = match compression {
+        ScriptCompression::None => "  return data;",
+        ScriptCompression::Gzip => "  return zlib.inflateSync(data);",
+        ScriptCompression::Xz => "  return runDecompressor(\"xz\", data);",
+        // zstd は Node.js 22.15 以降
+        ScriptCompression::Zstd => {
+            "  if (zlib.zstdDecompressSync) {
+    return zlib.zstdDecompressSync(data);
+  }
+  return runDecompressor(\"zstd\", data);"
+        }
+    };
+    let mut code = format!("function decompress(data) {{\n{}\n}}", body);
+    if let (ScriptCompression::Xz | ScriptCompression::Zstd, Some(command)) =
+        (compression, compression.command())
+    {
+        code.push_str(&NODE_RUN_DECOMPRESSOR.replace(
+            "{{MESSAGE}}",
+            &missing_command_message(command).replace(command, "${command}"),
+        ));
+    }
+    code
+}
+
+static NODE_RUN_DECOMPRESSOR: &str = r#"
+
+function runDecompressor(command, data) {
+  const { spawnSync } = require("child_process");
+  const result = spawnSync(command, ["-dc"], { input: data, maxBuffer: Infinity });
+  if (result.error) {
+    console.error(`{{MESSAGE}}`);
+    process.exit(1);
+  }
+  return result.stdout;
+}"#;
+
+/// テキストを圧縮して Base64 でテンプレートに埋め込み、展開処理を options に合わせる
+fn apply_compression(template: &str, text: &str, options: &ScriptOptions) -> Result<String> {
+    let encoded = general_purpose::STANDARD.encode(compress(text, options)?);
+    let (check, decompress) = decompress_snippets(options.shell, options.compression);
+    Ok(template
+        .replace("{{CHECK}}", &check)
+        .replace("{{DECOMPRESS}}", &decompress)
+        .replace("{{OUTPUT}}", &encoded))
+}
+
+pub static BASH_TEMPLATE_FOR_IMAGE: &str = r#"#!/bin/bash
+{{METADATA}}{{CHECK}}# BRAILLE_TEXT START
+BRAILLE_TEXT=$(cat <<'EOF' | base64 -d | {{DECOMPRESS}}
 {{OUTPUT}}
 EOF
 )
@@ -30,63 +453,631 @@ echo "$BRAILLE_TEXT"
 "#;
 
 pub static BASH_TEMPLATE_FOR_VIDEO: &str = r#"#!/bin/bash
-
-# BRAILLE_TEXT START
-BRAILLE_TEXT=$(cat <<'EOF' | base64 -d | gzip -d
+{{METADATA}}{{CHECK}}# BRAILLE_TEXT START
+BRAILLE_TEXT=$(cat <<'EOF' | base64 -d | {{DECOMPRESS}}
 {{OUTPUT}}
 EOF
 )
 # BRAILLE_TEXT END
 
-buffer=""
-declare -a array
+ESC=$'\e'
+FF=$'\f'
+# Number of plays (0 repeats until Ctrl-C)
+LOOP={{LOOP}}
+SETUP="{{SETUP}}"
+RESTORE="{{RESTORE}}"
+frame=""
+delay=""
 
-while IFS= read -r line; do
-    if [[ "$line" == *,* ]]; then
-        array+=("$buffer")
-        buffer="${line#*,}"
-    else
-        if [[ -n "$buffer" ]]; then
-            buffer+=$'\n'
-        fi
-        buffer+="$line"
+show_frame() {
+    if [[ -n "$delay" ]]; then
+        printf '%s' "$frame"
+        sleep "$delay"
     fi
-done <<< "$BRAILLE_TEXT"
+}
 
-if [[ -n "$buffer" ]]; then
-    array+=("$buffer")
-fi
+# Restore the terminal on exit, including Ctrl-C
+trap 'printf "%s" "$RESTORE"' EXIT
+trap 'exit 130' INT TERM
 
+printf '%s%s[2J' "$SETUP" "$ESC"
+count=0
+while (( LOOP == 0 || count < LOOP )); do
+    count=$((count + 1))
+    while IFS= read -r line; do
+        if [[ "$line" == "$FF"* ]]; then
+            show_frame
+            delay="${line#"$FF"}"
+            frame=""
+        else
+            frame+="$line"$'\n'
+        fi
+    done <<< "$BRAILLE_TEXT"
+    show_frame
+    delay=""
+    frame=""
+done
+"#;
 
-ESC=$(printf '\e')
-for item in "${array[@]}"; do
-    echo "${ESC}$item"
-    sleep {{SLEEP}}
+pub static SH_TEMPLATE_FOR_IMAGE: &str = r#"#!/bin/sh
+{{METADATA}}{{CHECK}}# BRAILLE_TEXT START
+BRAILLE_TEXT=$(cat <<'EOF'
+{{OUTPUT}}
+EOF
+)
+# BRAILLE_TEXT END
+
+printf '%s\n' "$BRAILLE_TEXT" | base64 -d | {{DECOMPRESS}}
+"#;
+
+pub static SH_TEMPLATE_FOR_VIDEO: &str = r#"#!/bin/sh
+{{METADATA}}{{CHECK}}# BRAILLE_TEXT START
+BRAILLE_TEXT=$(cat <<'EOF'
+{{OUTPUT}}
+EOF
+)
+# BRAILLE_TEXT END
+
+ESC=$(printf '\033')
+FF=$(printf '\f')
+# Number of plays (0 repeats until Ctrl-C)
+LOOP={{LOOP}}
+SETUP="{{SETUP}}"
+RESTORE="{{RESTORE}}"
+
+# Restore the terminal on exit, including Ctrl-C
+trap 'printf "%s" "$RESTORE"' EXIT
+trap 'exit 130' INT TERM
+
+printf '%s%s[2J' "$SETUP" "$ESC"
+count=0
+while [ "$LOOP" -eq 0 ] || [ "$count" -lt "$LOOP" ]; do
+    count=$((count + 1))
+    printf '%s\n' "$BRAILLE_TEXT" | base64 -d | {{DECOMPRESS}} | {
+        frame=""
+        delay=""
+        while IFS= read -r line; do
+            case "$line" in
+                "$FF"*)
+                    if [ -n "$delay" ]; then
+                        printf '%s' "$frame"
+                        sleep "$delay"
+                    fi
+                    delay="${line#"$FF"}"
+                    frame=""
+                    ;;
+                *)
+                    frame="$frame$line
+"
+                    ;;
+            esac
+        done
+        if [ -n "$delay" ]; then
+            printf '%s' "$frame"
+            sleep "$delay"
+        fi
+    }
 done
 "#;
 
-pub fn generate_bash_script_for_image(output: &str) -> io::Result<String> {
-    let encoded = gzip_and_base64_encode(output)?;
-    let script = BASH_TEMPLATE_FOR_IMAGE.replace("{{OUTPUT}}", &encoded);
+pub static FISH_TEMPLATE_FOR_IMAGE: &str = r#"#!/usr/bin/env fish
+{{METADATA}}{{CHECK}}# BRAILLE_TEXT START
+set BRAILLE_TEXT '{{OUTPUT}}'
+# BRAILLE_TEXT END
+
+printf '%s\n' $BRAILLE_TEXT | base64 -d | {{DECOMPRESS}}
+"#;
+
+pub static FISH_TEMPLATE_FOR_VIDEO: &str = r#"#!/usr/bin/env fish
+{{METADATA}}{{CHECK}}# BRAILLE_TEXT START
+set BRAILLE_TEXT '{{OUTPUT}}'
+# BRAILLE_TEXT END
+
+# Number of plays (0 repeats until Ctrl-C)
+set LOOP {{LOOP}}
+set SETUP {{SETUP}}
+set RESTORE {{RESTORE}}
+set frame
+set delay
+
+function show_frame --no-scope-shadowing
+    if test -n "$delay"
+        printf '%s\n' $frame
+        sleep $delay
+    end
+end
+
+# Restore the terminal on exit, including Ctrl-C
+function restore_terminal --on-event fish_exit
+    printf '%s' $RESTORE
+end
+function interrupt --on-signal INT --on-signal TERM
+    exit 130
+end
+
+set lines (printf '%s\n' $BRAILLE_TEXT | base64 -d | {{DECOMPRESS}})
+printf '%s%s[2J' $SETUP \e
+set count 0
+while test $LOOP -eq 0; or test $count -lt $LOOP
+    set count (math $count + 1)
+    for line in $lines
+        if string match -q -- \f'*' $line
+            show_frame
+            set delay (string sub -s 2 -- $line)
+            set frame
+        else
+            set -a frame $line
+        end
+    end
+    show_frame
+    set delay
+    set frame
+end
+"#;
+
+pub static POWERSHELL_TEMPLATE_FOR_IMAGE: &str = r#"#!/usr/bin/env pwsh
+{{METADATA}}# BRAILLE_TEXT START
+$BrailleText = '{{OUTPUT}}'
+# BRAILLE_TEXT END
+
+$bytes = [System.Convert]::FromBase64String($BrailleText)
+{{DECOMPRESS}}
+[Console]::OutputEncoding = [System.Text.Encoding]::UTF8
+[Console]::Write($text)
+"#;
+
+pub static POWERSHELL_TEMPLATE_FOR_VIDEO: &str = r#"#!/usr/bin/env pwsh
+{{METADATA}}# BRAILLE_TEXT START
+$BrailleText = '{{OUTPUT}}'
+# BRAILLE_TEXT END
+
+$bytes = [System.Convert]::FromBase64String($BrailleText)
+{{DECOMPRESS}}
+[Console]::OutputEncoding = [System.Text.Encoding]::UTF8
+$esc = [char]27
+# Number of plays (0 repeats until Ctrl-C)
+$loop = {{LOOP}}
+$setup = "{{SETUP}}"
+$restore = "{{RESTORE}}"
+$blocks = $text -split "`f" | Where-Object { $_ -ne '' }
+
+# Restore the terminal on exit, including Ctrl-C
+try {
+    [Console]::Write($setup + $esc + '[2J')
+    for ($count = 0; $loop -eq 0 -or $count -lt $loop; $count++) {
+        foreach ($block in $blocks) {
+            $header = $block.IndexOf("`n")
+            $delay = [double]::Parse($block.Substring(0, $header), [System.Globalization.CultureInfo]::InvariantCulture)
+            [Console]::Write($block.Substring($header + 1))
+            Start-Sleep -Milliseconds ($delay * 1000)
+        }
+    }
+} finally {
+    [Console]::Write($restore)
+}
+"#;
+
+pub static PYTHON_TEMPLATE_FOR_IMAGE: &str = r#"#!/usr/bin/env python3
+{{METADATA}}import base64
+import sys
+
+# BRAILLE_TEXT START
+BRAILLE_TEXT = "{{OUTPUT}}"
+# BRAILLE_TEXT END
+
+{{DECOMPRESS}}
+
+
+text = decompress(base64.b64decode(BRAILLE_TEXT)).decode("utf-8")
+sys.stdout.reconfigure(encoding="utf-8")
+print(text.rstrip("\n"))
+"#;
+
+pub static PYTHON_TEMPLATE_FOR_VIDEO: &str = r#"#!/usr/bin/env python3
+{{METADATA}}import base64
+import os
+import signal
+import sys
+import time
+
+# BRAILLE_TEXT START
+BRAILLE_TEXT = "{{OUTPUT}}"
+# BRAILLE_TEXT END
+
+# Number of plays (0 repeats until Ctrl-C)
+LOOP = {{LOOP}}
+SETUP = "{{SETUP}}"
+RESTORE = "{{RESTORE}}"
+
+
+{{DECOMPRESS}}
+
+
+def load_frames():
+    text = decompress(base64.b64decode(BRAILLE_TEXT)).decode("utf-8")
+    frames = []
+    for block in text.split("\f")[1:]:
+        delay, body = block.split("\n", 1)
+        frames.append((float(delay), body))
+    return frames
+
+
+def main():
+    frames = load_frames()
+    sys.stdout.reconfigure(encoding="utf-8")
+    if os.name == "nt":
+        # Enable escape sequences in the Windows console
+        os.system("")
+    # Treat TERM like Ctrl-C so that the terminal is restored
+    signal.signal(signal.SIGTERM, signal.default_int_handler)
+    sys.stdout.write(SETUP + "\x1b[2J")
+    try:
+        # Sleep until the deadline of each frame so that delays do not accumulate
+        deadline = time.monotonic()
+        count = 0
+        while frames and (LOOP == 0 or count < LOOP):
+            count += 1
+            for delay, body in frames:
+                sys.stdout.write(body)
+                sys.stdout.flush()
+                deadline += delay
+                time.sleep(max(0.0, deadline - time.monotonic()))
+    except KeyboardInterrupt:
+        return 130
+    finally:
+        sys.stdout.write(RESTORE)
+        sys.stdout.flush()
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
+"#;
+
+pub static NODE_TEMPLATE_FOR_IMAGE: &str = r#"#!/usr/bin/env node
+{{METADATA}}"use strict";
+const zlib = require("zlib");
+
+// BRAILLE_TEXT START
+const BRAILLE_TEXT = "{{OUTPUT}}";
+// BRAILLE_TEXT END
+
+{{DECOMPRESS}}
+
+const text = decompress(Buffer.from(BRAILLE_TEXT, "base64")).toString("utf8");
+process.stdout.write(text.replace(/\n+$/, "") + "\n");
+"#;
+
+pub static NODE_TEMPLATE_FOR_VIDEO: &str = r#"#!/usr/bin/env node
+{{METADATA}}"use strict";
+const zlib = require("zlib");
+const { performance } = require("perf_hooks");
+
+// BRAILLE_TEXT START
+const BRAILLE_TEXT = "{{OUTPUT}}";
+// BRAILLE_TEXT END
+
+// Number of plays (0 repeats until Ctrl-C)
+const LOOP = {{LOOP}};
+const SETUP = "{{SETUP}}";
+const RESTORE = "{{RESTORE}}";
+
+{{DECOMPRESS}}
+
+const text = decompress(Buffer.from(BRAILLE_TEXT, "base64")).toString("utf8");
+const frames = text.split("\f").slice(1).map((block) => {
+  const header = block.indexOf("\n");
+  return {
+    delay: parseFloat(block.slice(0, header)) * 1000,
+    body: block.slice(header + 1),
+  };
+});
+
+// Restore the terminal on exit, including Ctrl-C
+for (const signal of ["SIGINT", "SIGTERM"]) {
+  process.on(signal, () => {
+    process.stdout.write(RESTORE);
+    process.exit(130);
+  });
+}
+
+// Wait until the deadline of each frame so that delays do not accumulate
+let deadline = performance.now();
+let count = 0;
+let index = 0;
+
+function showFrame() {
+  if (index === frames.length) {
+    index = 0;
+    count++;
+  }
+  if (frames.length === 0 || (LOOP !== 0 && count >= LOOP)) {
+    process.stdout.write(RESTORE);
+    return;
+  }
+  const frame = frames[index++];
+  process.stdout.write(frame.body);
+  deadline += frame.delay;
+  setTimeout(showFrame, Math.max(0, deadline - performance.now()));
+}
+
+process.stdout.write(SETUP + "\x1b[2J");
+showFrame();
+"#;
+
+/// スクリプト先頭のメタデータの開始行と終了行 (コメントの記号を除いた部分)。
+/// その間の行は "key: value" の形式で、brailler inspect で読み出せる。
+pub const METADATA_START: &str = "BRAILLER_METADATA START";
+pub const METADATA_END: &str = "BRAILLER_METADATA END";
+
+/// 埋め込んだテキストの開始行と終了行
+pub const PAYLOAD_START: &str = "BRAILLE_TEXT START";
+pub const PAYLOAD_END: &str = "BRAILLE_TEXT END";
+
+/// シェルの行コメントの記号
+fn comment_prefix(shell: ScriptShell) -> &'static str {
+    match shell {
+        ScriptShell::Node => "//",
+        _ => "#",
+    }
+}
+
+/// ValueEnum のコマンドラインでの名前
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// メタデータのコメント行と、それに続く空行。fps は動画の場合のみ
+fn metadata_header(texts: &[&str], fps: Option<f32>, options: &ScriptOptions) -> String {
+    let cols = texts
+        .iter()
+        .flat_map(|text| text.lines())
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let rows = texts
+        .iter()
+        .map(|text| text.lines().count())
+        .max()
+        .unwrap_or(0);
+    let mut fields = vec![("version", env!("CARGO_PKG_VERSION").to_string())];
+    if let Some(source) = &options.source {
+        fields.push(("source", source.name.clone()));
+        fields.push(("sha256", source.sha256.clone()));
+        fields.push(("options", source.options.clone()));
+    }
+    fields.push(("size", format!("{}x{}", cols, rows)));
+    fields.push(("frames", texts.len().to_string()));
+    if let Some(fps) = fps {
+        fields.push(("fps", fps.to_string()));
+    }
+    fields.push(("shell", value_name(options.shell)));
+    fields.push(("compression", value_name(options.compression)));
+
+    let prefix = comment_prefix(options.shell);
+    let mut header = format!("{} {}\n", prefix, METADATA_START);
+    for (key, value) in fields {
+        // 改行などでコメントが途切れないよう、制御文字と行区切り文字は空白にする
+        // (U+2028 / U+2029 は JavaScript では改行として扱われる)
+        let value: String = value
+            .chars()
+            .map(|c| {
+                if c.is_control() || matches!(c, '\u{2028}' | '\u{2029}') {
+                    ' '
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let _ = writeln!(header, "{} {}: {}", prefix, key, value);
+    }
+    let _ = writeln!(header, "{} {}\n", prefix, METADATA_END);
+    header
+}
+
+/// スクリプト先頭のメタデータを (key, value) の並びとして読み出す。メタデータがない場合は空
+pub fn read_metadata(script: &str) -> Vec<(String, String)> {
+    script
+        .lines()
+        .map(|line| line.trim_start_matches(['#', '/']).trim_start_matches(' '))
+        .skip_while(|line| *line != METADATA_START)
+        .skip(1)
+        .take_while(|line| *line != METADATA_END)
+        .filter_map(|line| line.split_once(": "))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+pub fn generate_script_for_image(output: &str, options: &ScriptOptions) -> Result<String> {
+    let template = match options.shell {
+        ScriptShell::Sh => SH_TEMPLATE_FOR_IMAGE,
+        ScriptShell::Bash => BASH_TEMPLATE_FOR_IMAGE,
+        ScriptShell::Fish => FISH_TEMPLATE_FOR_IMAGE,
+        ScriptShell::Powershell => POWERSHELL_TEMPLATE_FOR_IMAGE,
+        ScriptShell::Python => PYTHON_TEMPLATE_FOR_IMAGE,
+        ScriptShell::Node => NODE_TEMPLATE_FOR_IMAGE,
+    };
+    let script = apply_compression(template, output, options)?
+        .replace("{{METADATA}}", &metadata_header(&[output], None, options));
     Ok(script)
 }
 
-pub fn generate_bash_script_for_video(output: &str, sleep: f32) -> io::Result<String> {
-    let encoded = gzip_and_base64_encode(output)?;
-    let script = BASH_TEMPLATE_FOR_VIDEO
-        .replace("{{OUTPUT}}", &encoded)
-        .replace("{{SLEEP}}", &sleep.to_string());
+/// 動画のペイロードでフレームの先頭を表す文字 (form feed)。
+/// 各フレームは FRAME_SEPARATOR と表示時間 (秒) の行に続けて、そのまま端末に出力する内容が並ぶ。
+/// キーフレームはカーソルを左上に戻すエスケープとフレームの各行、
+/// 差分フレームは変更されたセルの書き換えと、最終行の先頭へのカーソル移動の1行になる。
+/// どちらも最後の改行でカーソルがフレームの下に移動する。
+///
+/// ```text
+/// \x0c0.100
+/// \x1b[H⣿⣿⠀⠀
+/// ⠀⠀⣿⣿
+/// \x0c0.250
+/// \x1b[1;1H⠀⠀\x1b[2;3H⠀⠀\x1b[2;1H
+/// ...
+/// ```
+pub const FRAME_SEPARATOR: char = '\x0c';
+
+/// フレームを FRAME_SEPARATOR で区切ったペイロードにする。
+/// 表示時間は次のフレームの pts との差で、最後のフレームは 1/fps 秒表示する。
+/// options.keyframe_interval が指定されている場合、キーフレーム以外は前フレームとの差分にする。
+pub fn encode_frames(frames: &[TextFrame], fps: f32, options: &ScriptOptions) -> Result<String> {
+    // 丸め誤差が蓄積しないよう pts をミリ秒に丸めてから差を取る
+    let millis = |pts: Duration| (pts.as_secs_f64() * 1000.0).round() as u64;
+    let last_delay = frame_interval(fps)?;
+    let mut payload = String::new();
+    let mut previous: Vec<Vec<char>> = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let next = frames
+            .get(i + 1)
+            .map(|next| next.pts)
+            .unwrap_or(frame.pts + last_delay);
+        let delay = millis(next).saturating_sub(millis(frame.pts));
+        let _ = writeln!(
+            payload,
+            "{}{}.{:03}",
+            FRAME_SEPARATOR,
+            delay / 1000,
+            delay % 1000
+        );
+        // 区切り文字と紛れないよう、フレーム中の form feed は取り除く
+        let text: String = frame
+            .text
+            .chars()
+            .filter(|&c| c != FRAME_SEPARATOR)
+            .collect();
+        let cells: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+        let mut body = String::new();
+        if options
+            .keyframe_interval
+            .is_some_and(|interval| !i.is_multiple_of(interval))
+            && same_shape(&previous, &cells)
+        {
+            for (y, (prev, next)) in previous.iter().zip(&cells).enumerate() {
+                write_changed_runs(&mut body, y, prev, next);
+            }
+            let _ = writeln!(body, "\x1b[{};1H", cells.len());
+        }
+        // 差分の方が長くなる (ほぼ全体が変わる) 場合はキーフレームにする
+        if body.is_empty() || body.len() >= text.len() {
+            body = format!("\x1b[H{}", text);
+            if !body.ends_with('\n') {
+                body.push('\n');
+            }
+        }
+        payload.push_str(&body);
+        previous = cells;
+    }
+    Ok(payload)
+}
+
+/// encode_frames のペイロードから各フレームを復元する。差分フレームは前のフレームに変更を適用する。
+/// 表示時間が負の値や数値でない場合、pts が Duration に収まらない場合はエラー
+pub fn decode_frames(payload: &str) -> Result<Vec<TextFrame>> {
+    let mut frames = Vec::new();
+    let mut pts = Duration::ZERO;
+    let mut screen: Vec<Vec<char>> = Vec::new();
+    for block in payload.split(FRAME_SEPARATOR).skip(1) {
+        let (delay, body) = block.split_once('\n').unwrap_or((block, ""));
+        match body.strip_prefix("\x1b[H") {
+            Some(text) => screen = text.lines().map(|line| line.chars().collect()).collect(),
+            None => apply_changed_runs(&mut screen, body),
+        }
+        let text = screen
+            .iter()
+            .map(|line| line.iter().collect::<String>() + "\n")
+            .collect();
+        frames.push(TextFrame { pts, text });
+        let delay = delay.trim();
+        pts = delay
+            .parse()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .and_then(|delay| pts.checked_add(delay))
+            .ok_or_else(|| BraillerError::Decode(format!("invalid frame delay {:?}", delay)))?;
+    }
+    Ok(frames)
+}
+
+/// line の表示上の桁 column にあるセルの位置
+fn cell_index(line: &[char], column: usize) -> usize {
+    let mut width = 0;
+    line.iter()
+        .position(|&c| {
+            width += char_width(c);
+            width > column
+        })
+        .unwrap_or(line.len())
+}
+
+/// write_changed_runs が出力したカーソル移動と文字を screen に書き込む。
+/// カーソル移動の桁は表示幅なので、セルの位置に変換する
+fn apply_changed_runs(screen: &mut [Vec<char>], body: &str) {
+    let (mut row, mut col) = (0, 0);
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                let sequence: String = chars.by_ref().take_while(|&c| c != 'H').collect();
+                let mut position = sequence
+                    .trim_start_matches('[')
+                    .split(';')
+                    .map(|n| n.parse::<usize>().unwrap_or(1));
+                row = position.next().unwrap_or(1).saturating_sub(1);
+                let column = position.next().unwrap_or(1).saturating_sub(1);
+                col = screen.get(row).map_or(0, |line| cell_index(line, column));
+            }
+            '\n' => (row, col) = (row + 1, 0),
+            c => {
+                if let Some(cell) = screen.get_mut(row).and_then(|line| line.get_mut(col)) {
+                    *cell = c;
+                }
+                col += 1;
+            }
+        }
+    }
+}
+
+/// 各フレームを pts どおりのタイミングで、画面の左上から上書きして表示するスクリプトを生成する。
+/// スクリプトは options.loops 回再生し、終了時 (Ctrl-C を含む) にカーソルと画面を元に戻す。
+pub fn generate_script_for_video(
+    frames: &[TextFrame],
+    fps: f32,
+    options: &ScriptOptions,
+) -> Result<String> {
+    let template = match options.shell {
+        ScriptShell::Sh => SH_TEMPLATE_FOR_VIDEO,
+        ScriptShell::Bash => BASH_TEMPLATE_FOR_VIDEO,
+        ScriptShell::Fish => FISH_TEMPLATE_FOR_VIDEO,
+        ScriptShell::Powershell => POWERSHELL_TEMPLATE_FOR_VIDEO,
+        ScriptShell::Python => PYTHON_TEMPLATE_FOR_VIDEO,
+        ScriptShell::Node => NODE_TEMPLATE_FOR_VIDEO,
+    };
+    let script = apply_compression(template, &encode_frames(frames, fps, options)?, options)?;
+    let (setup, restore) = options.terminal_sequences();
+    let texts: Vec<&str> = frames.iter().map(|frame| frame.text.as_str()).collect();
+    // ファイル名などにプレースホルダーの文字列が含まれていても置換されないよう、メタデータは最後に埋め込む
+    let script = script
+        .replace("{{LOOP}}", &options.loops.to_string())
+        .replace("{{SETUP}}", &shell_escapes(options.shell, &setup))
+        .replace("{{RESTORE}}", &shell_escapes(options.shell, &restore))
+        .replace("{{METADATA}}", &metadata_header(&texts, Some(fps), options));
     Ok(script)
 }
 
-pub fn save_script(script: &str, path: &str) -> io::Result<()> {
-    let mut file = std::fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .mode(0o755)
-        .open(path)?;
-    file.write_all(script.as_bytes())?;
-    file.sync_all()?;
-    Ok(())
+pub fn save_script(script: &str, path: &str) -> Result<()> {
+    let write = || -> io::Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o755)
+            .open(path)?;
+        file.write_all(script.as_bytes())?;
+        file.sync_all()
+    };
+    write().map_err(|e| BraillerError::io(path, e))
 }
@@ -15,9 +15,7 @@ impl FromStr for Size {
         let separators = ['x', ','];
         let parts: Vec<&str> = s.split(|c| separators.contains(&c)).collect();
         if parts.len() != 2 {
-            return Err(
-                "サイズは WIDTHxHEIGHT または WIDTH,HEIGHT の形式で指定してください".into(),
-            );
+            return Err("Size must be WIDTHxHEIGHT or WIDTH,HEIGHT".into());
         }
         let width = parts[0].trim().parse::<u32>().map_err(|e| e.to_string())?;
         let height = parts[1].trim().parse::<u32>().map_err(|e| e.to_string())?;
@@ -0,0 +1,162 @@
+use std::fs;
+use std::time::Duration;
+
+use crate::error::{BraillerError, Result};
+use crate::terminal::{char_width, display_width};
+use crate::timeline::Timeline;
+use crate::timestamp::Timestamp;
+
+/// 字幕表示に使う最大行数。これを超える行は表示しない
+const MAX_LINES: usize = 3;
+
+/// 字幕の1項目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+/// SRT / WebVTT の字幕
+#[derive(Debug, Clone, Default)]
+pub struct Subtitles {
+    cues: Vec<Cue>,
+    /// 字幕の表示に使う行数 (最も行数の多い項目に合わせる)
+    lines: usize,
+}
+
+/// SRT (00:00:01,000) と WebVTT (00:00:01.000 / 00:01.000) の時刻をパースする
+fn parse_time(text: &str) -> Option<Duration> {
+    text.trim()
+        .replace(',', ".")
+        .parse::<Timestamp>()
+        .ok()
+        .map(|t| t.0)
+}
+
+/// <i> などのタグと {\an8} などの ASS 形式の指定を取り除き、WebVTT の文字参照を戻す
+fn strip_markup(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut closing = None;
+    for c in line.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (Some(end), c) if c == end => closing = None,
+            (Some(_), _) => {}
+            (None, c) => text.push(c),
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+impl Subtitles {
+    /// SRT または WebVTT の字幕をパースする。時刻の行 ("-->" を含む行) を持たないブロック
+    /// (WebVTT のヘッダーや NOTE など) は無視する。
+    pub fn parse(content: &str) -> Result<Self> {
+        let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        let mut cues = Vec::new();
+        for block in content.split("\n\n") {
+            let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+            let Some(timing) = lines.next() else {
+                continue;
+            };
+            let (start, rest) = timing.split_once("-->").unwrap_or_default();
+            // WebVTT は終了時刻の後に表示位置などの設定が続く
+            let end = rest.split_whitespace().next().unwrap_or_default();
+            let (Some(start), Some(end)) = (parse_time(start), parse_time(end)) else {
+                return Err(BraillerError::Decode(format!(
+                    "invalid subtitle timing: {}",
+                    timing
+                )));
+            };
+            let text = lines
+                .map(strip_markup)
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            if !text.is_empty() {
+                cues.push(Cue { start, end, text });
+            }
+        }
+        cues.sort_by_key(|cue| cue.start);
+        let lines = cues
+            .iter()
+            .map(|cue| cue.text.lines().count())
+            .max()
+            .unwrap_or(0)
+            .min(MAX_LINES);
+        Ok(Self { cues, lines })
+    }
+
+    /// 字幕ファイルを読み込む
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| BraillerError::io(path, e))?;
+        Self::parse(&content)
+    }
+
+    pub fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    /// 字幕の表示に使う行数
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// 字幕の時刻を、切り出し・速度変更後の出力フレームの時刻に合わせる
+    pub fn retime(mut self, timeline: &Timeline) -> Self {
+        for cue in &mut self.cues {
+            cue.start = timeline.to_output(cue.start);
+            cue.end = timeline.to_output(cue.end);
+        }
+        // 切り出し範囲より前に終わる項目は表示されない
+        self.cues.retain(|cue| !cue.end.is_zero());
+        self
+    }
+
+    /// pts の時点で表示する字幕。複数の項目が重なる場合は改行でつなぐ
+    pub fn text_at(&self, pts: Duration) -> Option<String> {
+        let texts: Vec<&str> = self
+            .cues
+            .iter()
+            .take_while(|cue| cue.start <= pts)
+            .filter(|cue| pts < cue.end)
+            .map(|cue| cue.text.as_str())
+            .collect();
+        (!texts.is_empty()).then(|| texts.join("\n"))
+    }
+
+    /// pts の時点の字幕を表示幅 width (全角文字は2桁) に中央揃えした lines() 行の文字列にする。
+    /// 字幕がない時点も同じ行数の空行を返すため、フレームの大きさが変わらない。
+    pub fn render_area(&self, pts: Duration, width: usize) -> String {
+        let text = self.text_at(pts).unwrap_or_default();
+        let mut lines: Vec<&str> = text.lines().take(self.lines).collect();
+        // 字幕は下に寄せる
+        while lines.len() < self.lines {
+            lines.insert(0, "");
+        }
+        let mut area = String::new();
+        for line in lines {
+            // 幅に収まらない部分は切り捨てる
+            let mut used = 0;
+            let line: String = line
+                .chars()
+                .take_while(|c| {
+                    used += char_width(*c);
+                    used <= width
+                })
+                .collect();
+            let padding = width - display_width(&line);
+            area.push_str(&" ".repeat(padding / 2));
+            area.push_str(&line);
+            area.push_str(&" ".repeat(padding - padding / 2));
+            area.push('\n');
+        }
+        area
+    }
+}
@@ -0,0 +1,243 @@
+use std::env;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::fd::AsRawFd;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use unicode_width::UnicodeWidthChar;
+
+/// 同期出力モード (DEC 2026) の開始・終了。対応端末は終了までの描画をまとめて反映する
+const BEGIN_SYNCHRONIZED_UPDATE: &str = "\x1B[?2026h";
+const END_SYNCHRONIZED_UPDATE: &str = "\x1B[?2026l";
+
+/// 変更セルの間にある未変更セルがこの数以下なら、カーソル移動せずに続けて書き直す。
+/// カーソル移動のエスケープ (約8バイト) は Braille 文字 (3バイト) 数個分に相当するため。
+const MAX_GAP: usize = 2;
+
+/// 標準出力の端末が同期出力モードに対応していそうかを判定する。
+/// 未対応の端末でも未知のモード設定は無視されるため、明らかに非対応なもの以外は有効にする。
+pub fn supports_synchronized_output() -> bool {
+    if !std::io::stdout().is_terminal() {
+        return false;
+    }
+    let term = env::var("TERM").unwrap_or_default();
+    !matches!(term.as_str(), "" | "dumb" | "linux")
+}
+
+/// 前フレームのセルを保持し、変更されたセルだけを書き換えるエスケープシーケンスを生成する
+pub struct DiffRenderer {
+    previous: Vec<Vec<char>>,
+    synchronized: bool,
+}
+
+impl DiffRenderer {
+    pub fn new(synchronized: bool) -> Self {
+        Self {
+            previous: Vec::new(),
+            synchronized,
+        }
+    }
+
+    /// 次回の描画で画面全体を描き直す
+    pub fn invalidate(&mut self) {
+        self.previous.clear();
+    }
+
+    /// frame を表示するための出力を返す。
+    /// 前フレームと行数・桁数が同じ場合は変更されたセルのみ、異なる場合は画面全体を描画する。
+    /// 前フレームから変更がない場合は空文字列を返す。
+    pub fn render(&mut self, frame: &str) -> String {
+        let cells: Vec<Vec<char>> = frame.lines().map(|line| line.chars().collect()).collect();
+        let mut changes = String::new();
+        if same_shape(&self.previous, &cells) {
+            for (y, (prev, next)) in self.previous.iter().zip(&cells).enumerate() {
+                write_changed_runs(&mut changes, y, prev, next);
+            }
+            if changes.is_empty() {
+                return changes;
+            }
+        } else {
+            changes.push_str("\x1B[2J\x1B[1;1H");
+            for line in &cells {
+                changes.extend(line.iter());
+                changes.push('\n');
+            }
+        }
+
+        let mut output = String::new();
+        if self.synchronized {
+            output.push_str(BEGIN_SYNCHRONIZED_UPDATE);
+        }
+        output.push_str(&changes);
+        // カーソルはフレームの下に置く
+        let _ = write!(output, "\x1B[{};1H", cells.len() + 1);
+
+        if self.synchronized {
+            output.push_str(END_SYNCHRONIZED_UPDATE);
+        }
+        self.previous = cells;
+        output
+    }
+}
+
+/// 文字の表示幅 (全角文字は2桁)
+pub fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// 文字列の表示幅
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// 2つのフレームの行数と、各行のセルの数・表示幅が同じかを判定する。
+/// 全角文字の位置が変わると後ろのセルの表示位置がずれるため、セルごとの幅も比較する
+pub fn same_shape(prev: &[Vec<char>], next: &[Vec<char>]) -> bool {
+    prev.len() == next.len()
+        && prev.iter().zip(next).all(|(prev, next)| {
+            prev.len() == next.len()
+                && prev
+                    .iter()
+                    .zip(next)
+                    .all(|(&a, &b)| char_width(a) == char_width(b))
+        })
+}
+
+/// 1行分の変更箇所を、カーソル移動と書き換える文字の並びとして出力する。
+/// カーソルの桁は、前にあるセルの表示幅の合計から求める
+pub fn write_changed_runs(output: &mut String, y: usize, prev: &[char], next: &[char]) {
+    let mut x = 0;
+    let mut column = 0;
+    while x < next.len() {
+        if prev[x] == next[x] {
+            column += char_width(next[x]);
+            x += 1;
+            continue;
+        }
+        // 変更の連続区間を、短い未変更区間を挟んでまとめる
+        let start = x;
+        let mut end = x + 1;
+        let mut scan = end;
+        while scan < next.len() && scan - end <= MAX_GAP {
+            if prev[scan] != next[scan] {
+                end = scan + 1;
+            }
+            scan += 1;
+        }
+        let _ = write!(output, "\x1B[{};{}H", y + 1, column + 1);
+        output.extend(&next[start..end]);
+        column += next[start..end]
+            .iter()
+            .map(|&c| char_width(c))
+            .sum::<usize>();
+        x = end;
+    }
+}
+
+/// 対話モードで受け付けるキー入力
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Space,
+    Left,
+    Right,
+    Plus,
+    Minus,
+    Char(char),
+    /// Ctrl-C
+    Interrupt,
+}
+
+/// 端末から読み込んだバイト列をキー入力に変換する
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let key = match bytes[i] {
+            0x1B if bytes.get(i + 1) == Some(&b'[') => {
+                i += 2;
+                match bytes.get(i) {
+                    Some(b'C') => Some(Key::Right),
+                    Some(b'D') => Some(Key::Left),
+                    _ => None,
+                }
+            }
+            0x03 => Some(Key::Interrupt),
+            b' ' => Some(Key::Space),
+            b'+' | b'=' => Some(Key::Plus),
+            b'-' | b'_' => Some(Key::Minus),
+            b if b.is_ascii_graphic() => Some(Key::Char(b as char)),
+            _ => None,
+        };
+        keys.extend(key);
+        i += 1;
+    }
+    keys
+}
+
+/// 端末 (/dev/tty) をエコーなし・行バッファなし・シグナル無効のモードにし、
+/// カーソルを隠す。Drop 時に元の状態に戻す。
+/// Ctrl-C はシグナルではなく Key::Interrupt として届くため、終了時に必ず端末が復元される。
+pub struct RawTerminal {
+    tty: File,
+    original: libc::termios,
+}
+
+impl RawTerminal {
+    pub fn enable() -> io::Result<Self> {
+        let tty = File::options().read(true).write(true).open("/dev/tty")?;
+        let fd = tty.as_raw_fd();
+        let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: fd は開いている端末で、termios は tcgetattr が初期化する
+        let original = unsafe {
+            if libc::tcgetattr(fd, termios.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios.assume_init()
+        };
+        let mut raw = original;
+        // 出力の改行変換 (OPOST) は残し、入力のみ1バイトずつ読めるようにする
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: raw は tcgetattr で取得した値を変更したもの
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut terminal = Self { tty, original };
+        // カーソルを隠す
+        terminal.tty.write_all(b"\x1B[?25l")?;
+        Ok(terminal)
+    }
+
+    /// キー入力を読み込むスレッドを起動し、受信側を返す
+    pub fn keys(&self) -> io::Result<Receiver<Key>> {
+        let mut tty = self.tty.try_clone()?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            while let Ok(n) = tty.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                for key in parse_keys(&buf[..n]) {
+                    if tx.send(key).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(rx)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        // カーソルを表示して端末設定を戻す
+        let _ = self.tty.write_all(b"\x1B[?25h");
+        // SAFETY: original は enable で取得した元の設定
+        unsafe {
+            libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.original);
+        }
+    }
+}
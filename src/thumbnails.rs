@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use crate::animation;
+use crate::args::{BinarizeOption, FrameOptions, RenderOptions, ThumbnailsArgs};
+use crate::braille::generate_braille;
+use crate::error::{BraillerError, Result};
+use crate::file_type::{self, FileType};
+use crate::image_processing::binarize;
+use crate::input;
+use crate::montage::{Tile, layout};
+use crate::player::Frame;
+use crate::size::Size;
+use crate::timeline::Timeline;
+use crate::timestamp::Timestamp;
+
+/// --size が 0x0 の場合のサムネイルの高さ (文字数)
+pub const DEFAULT_ROWS: u32 = 16;
+/// 輝度ヒストグラムのビン数
+pub const BINS: usize = 32;
+
+/// シーンの開始時刻と、サムネイルにする中央付近のフレーム。
+/// メモリ使用量を抑えるため、シーンのフレームはすべては保持しない
+pub struct Scene {
+    pub start: Duration,
+    /// シーンのフレーム数
+    pub frame_count: usize,
+    /// 2 のべき乗番目 (0, 1, 2, 4, 8, ...) のフレームのうち、最後の2つ
+    earlier: Option<Frame>,
+    later: Frame,
+    later_index: usize,
+}
+
+impl Scene {
+    fn new(frame: Frame) -> Self {
+        Self {
+            start: frame.pts,
+            frame_count: 1,
+            earlier: None,
+            later: frame,
+            later_index: 0,
+        }
+    }
+
+    fn push(&mut self, frame: Frame) {
+        let index = self.frame_count;
+        self.frame_count += 1;
+        if index.is_power_of_two() {
+            self.earlier = Some(std::mem::replace(&mut self.later, frame));
+            self.later_index = index;
+        }
+    }
+
+    /// 保持している2つのフレームのうち、シーンの中央に近いほう。
+    /// 中央のフレームとの差はシーンのフレーム数の 1/4 程度に収まる
+    pub fn frame(&self) -> &Frame {
+        let middle = self.frame_count / 2;
+        match &self.earlier {
+            Some(earlier)
+                if middle.abs_diff(self.later_index / 2) < middle.abs_diff(self.later_index) =>
+            {
+                earlier
+            }
+            _ => &self.later,
+        }
+    }
+}
+
+/// 輝度ヒストグラム
+pub fn histogram(frame: &Frame) -> [u32; BINS] {
+    let mut bins = [0; BINS];
+    for pixel in frame.image.pixels() {
+        bins[pixel.0[0] as usize * BINS / 256] += 1;
+    }
+    bins
+}
+
+/// 2つのヒストグラムの差。同じ分布で 0.0、重なりがない場合に 1.0 になる
+pub fn difference(a: &[u32; BINS], b: &[u32; BINS]) -> f32 {
+    let total = a.iter().sum::<u32>().max(1) as f32;
+    let diff: u32 = a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).sum();
+    diff as f32 / (2.0 * total)
+}
+
+/// 連続するフレームの輝度ヒストグラムの差が threshold を超えた位置でシーンを区切る。
+/// min_scene より短いシーンは区切らずに前のシーンに含める。
+pub fn detect_scenes<I>(frames: I, threshold: f32, min_scene: Duration) -> Result<Vec<Scene>>
+where
+    I: Iterator<Item = Result<Frame>>,
+{
+    let mut scenes: Vec<Scene> = Vec::new();
+    let mut previous = None;
+    for frame in frames {
+        let frame = frame?;
+        let hist = histogram(&frame);
+        let cut = match (previous, scenes.last()) {
+            (Some(previous), Some(scene)) => {
+                difference(&previous, &hist) > threshold
+                    && frame.pts.saturating_sub(scene.start) >= min_scene
+            }
+            _ => true,
+        };
+        previous = Some(hist);
+        match scenes.last_mut() {
+            Some(scene) if !cut => scene.push(frame),
+            _ => scenes.push(Scene::new(frame)),
+        }
+    }
+    Ok(scenes)
+}
+
+/// 動画・アニメーションのフレームは二値化せずに受け取り、ヒストグラムの計算に使う
+fn frame_options(args: &ThumbnailsArgs) -> Result<FrameOptions> {
+    let size = match args.render.size {
+        Size(0, 0) => Size(0, DEFAULT_ROWS),
+        size => size,
+    };
+    Ok(FrameOptions {
+        render: RenderOptions {
+            size,
+            binarize: BinarizeOption::None,
+            ..args.render.clone()
+        },
+        timeline: Timeline::with_fps(args.fps)?,
+        dump_frames: String::new(),
+        dump_every: 1,
+    })
+}
+
+#[cfg(not(feature = "video"))]
+fn detect_video_scenes(
+    args: &ThumbnailsArgs,
+    _data: Option<&[u8]>,
+    _options: FrameOptions,
+) -> Result<(Vec<Scene>, Size)> {
+    Err(BraillerError::UnsupportedFormat(format!(
+        "{} is a video, but video support is disabled (rebuild with --features video)",
+        args.input
+    )))
+}
+#[cfg(feature = "video")]
+fn detect_video_scenes(
+    args: &ThumbnailsArgs,
+    data: Option<&[u8]>,
+    options: FrameOptions,
+) -> Result<(Vec<Scene>, Size)> {
+    // ffmpeg はファイルパスを必要とするため、標準入力は一時ファイルに書き出す
+    let (video_path, _temp_input) = input::file_path(&args.input, data)?;
+    let video_data = crate::video::load_frames(&video_path, options)?;
+    let scenes = detect_scenes(video_data.frames, args.threshold, args.min_scene.0)?;
+    Ok((scenes, video_data.size))
+}
+
+/// 動画・アニメーションのシーンの切り替わりを検出し、シーンごとに中央付近のフレームを
+/// 開始時刻のキャプション付きで並べて出力する
+pub fn run_thumbnails(args: &ThumbnailsArgs) -> Result<()> {
+    if !(0.0..=1.0).contains(&args.threshold) {
+        return Err(BraillerError::InvalidArgument(format!(
+            "--threshold {} must be between 0.0 and 1.0",
+            args.threshold
+        )));
+    }
+    let options = frame_options(args)?;
+    // 動画ファイルは全体を読み込まず、パスのまま処理する
+    let (scenes, size) =
+        if !input::is_stdin(&args.input) && file_type::infer_type(&args.input) == FileType::Video {
+            detect_video_scenes(args, None, options)?
+        } else {
+            let data = input::read_input(&args.input)?;
+            match file_type::infer_type_from_bytes(&data) {
+                FileType::Animation => {
+                    let animation_data = animation::load_frames(&data, &options)?;
+                    let scenes =
+                        detect_scenes(animation_data.frames, args.threshold, args.min_scene.0)?;
+                    (scenes, animation_data.size)
+                }
+                FileType::Video => detect_video_scenes(args, Some(&data), options)?,
+                _ => {
+                    return Err(BraillerError::UnsupportedFormat(format!(
+                        "{} is neither a video nor an animation",
+                        args.input
+                    )));
+                }
+            }
+        };
+
+    let (cols, rows) = (size.0 / 2, size.1 / 4);
+    let tiles = scenes
+        .iter()
+        .map(|scene| {
+            let image = binarize(&scene.frame().image, args.render.binarize);
+            Ok(Tile {
+                text: generate_braille(&image, cols, rows)?,
+                caption: Timestamp(scene.start).to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    print!("{}", layout(&tiles, args.columns, args.gap, true));
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use crate::args::Args;
+use crate::error::{BraillerError, Result};
+
+/// --speed に指定できる範囲。極端な値では時刻の変換が Duration に収まらなくなる
+const SPEED_RANGE: RangeInclusive<f32> = 0.01..=100.0;
+
+/// 入力の動画・アニメーションから切り出す範囲と、出力の再生速度・フレームレート。
+/// 出力フレームの pts は切り出し開始位置を 0 とし、再生速度で割った時刻になる。
+#[derive(Debug, Clone, Copy)]
+pub struct Timeline {
+    /// 切り出し開始位置 (入力の時刻)
+    pub start: Duration,
+    /// 切り出し終了位置 (入力の時刻)
+    pub end: Option<Duration>,
+    pub speed: f64,
+    /// 出力のフレーム間隔。None の場合はすべてのフレームを出力する
+    pub sample_interval: Option<Duration>,
+}
+
+impl Timeline {
+    /// 入力全体を等速で出力する。fps が 0 の場合はすべてのフレームを出力する
+    pub fn with_fps(fps: f32) -> Result<Self> {
+        Ok(Self {
+            start: Duration::ZERO,
+            end: None,
+            speed: 1.0,
+            sample_interval: sample_interval(fps)?,
+        })
+    }
+
+    pub fn new(args: &Args) -> Result<Self> {
+        if !SPEED_RANGE.contains(&args.speed) {
+            return Err(BraillerError::InvalidArgument(format!(
+                "--speed {} must be between {} and {}",
+                args.speed,
+                SPEED_RANGE.start(),
+                SPEED_RANGE.end()
+            )));
+        }
+        let sample_interval = sample_interval(args.fps)?;
+        let start = args.start.0;
+        let end = match (args.duration, args.end) {
+            (Some(duration), _) => Some(start.checked_add(duration.0).ok_or_else(|| {
+                BraillerError::InvalidArgument(format!(
+                    "--start {} plus --duration {} is out of range",
+                    args.start, duration
+                ))
+            })?),
+            (None, Some(end)) if end.0 <= start => {
+                return Err(BraillerError::InvalidArgument(format!(
+                    "--end {} is not after --start {}",
+                    end, args.start
+                )));
+            }
+            (None, end) => end.map(|end| end.0),
+        };
+        Ok(Self {
+            start,
+            end,
+            speed: args.speed as f64,
+            sample_interval,
+        })
+    }
+
+    /// 入力の時刻を出力の時刻に変換する。Duration に収まらない場合は Duration::MAX になる
+    pub fn to_output(&self, source: Duration) -> Duration {
+        let secs = source.saturating_sub(self.start).as_secs_f64() / self.speed;
+        Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX)
+    }
+
+    /// 出力の時刻を入力の時刻に変換する。Duration に収まらない場合は Duration::MAX になる
+    pub fn to_source(&self, output: Duration) -> Duration {
+        let scaled =
+            Duration::try_from_secs_f64(output.as_secs_f64() * self.speed).unwrap_or(Duration::MAX);
+        self.start.saturating_add(scaled)
+    }
+
+    /// 出力のフレームレート
+    pub fn output_fps(&self, source_fps: f32) -> Result<f32> {
+        let fps = match self.sample_interval {
+            Some(interval) => 1.0 / interval.as_secs_f32(),
+            None => source_fps * self.speed as f32,
+        };
+        frame_interval(fps)?;
+        Ok(fps)
+    }
+
+    /// 出力の長さ
+    pub fn output_duration(&self, source_duration: Option<Duration>) -> Option<Duration> {
+        let end = match (self.end, source_duration) {
+            (Some(end), Some(total)) => end.min(total),
+            (end, total) => end.or(total)?,
+        };
+        Some(self.to_output(end))
+    }
+}
+
+/// fps に対応するフレーム間隔。
+/// fps が正の有限値でない場合 (0/0 のフレームレートなど) や、間隔が Duration に収まらない場合はエラー
+pub fn frame_interval(fps: f32) -> Result<Duration> {
+    match Duration::try_from_secs_f32(1.0 / fps) {
+        Ok(interval) if fps > 0.0 && !interval.is_zero() => Ok(interval),
+        _ => Err(BraillerError::Decode(format!("invalid frame rate {}", fps))),
+    }
+}
+
+/// --fps に対応する出力のフレーム間隔。0 の場合は None
+fn sample_interval(fps: f32) -> Result<Option<Duration>> {
+    if !(fps >= 0.0 && fps.is_finite()) {
+        return Err(BraillerError::InvalidArgument(format!(
+            "--fps {} must be 0 or positive",
+            fps
+        )));
+    }
+    if fps == 0.0 {
+        return Ok(None);
+    }
+    // 小さすぎる fps はフレーム間隔が Duration に収まらない
+    match Duration::try_from_secs_f64(1.0 / fps as f64) {
+        Ok(interval) if !interval.is_zero() => Ok(Some(interval)),
+        _ => Err(BraillerError::InvalidArgument(format!(
+            "--fps {} is out of range",
+            fps
+        ))),
+    }
+}
+
+/// 入力フレームを出力するかの判定結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sample {
+    /// 出力の時刻 pts のフレームとして出力する
+    Emit(Duration),
+    /// 切り出し範囲外、またはフレームレートを合わせるために捨てる
+    Skip,
+    /// 切り出し終了位置を過ぎたので以降のフレームは不要
+    End,
+}
+
+/// 入力フレームを時刻順に受け取り、切り出し範囲とフレームレートに合うものを選ぶ
+pub struct Sampler {
+    timeline: Timeline,
+    /// この位置 (入力の時刻) より前のフレームは出力しない
+    position: Duration,
+    /// 次に出力するフレームの時刻 (フレームレートを指定した場合)
+    next_sample: Duration,
+}
+
+impl Sampler {
+    /// 出力の時刻 position から出力を始める
+    pub fn new(timeline: Timeline, position: Duration) -> Self {
+        Self {
+            timeline,
+            position: timeline.to_source(position),
+            next_sample: position,
+        }
+    }
+
+    /// 出力を始める位置 (入力の時刻)
+    pub fn source_position(&self) -> Duration {
+        self.position
+    }
+
+    pub fn sample(&mut self, source_pts: Duration) -> Sample {
+        if self.timeline.end.is_some_and(|end| source_pts >= end) {
+            return Sample::End;
+        }
+        if source_pts < self.position {
+            return Sample::Skip;
+        }
+        let pts = self.timeline.to_output(source_pts);
+        if let Some(interval) = self.timeline.sample_interval {
+            if pts < self.next_sample {
+                return Sample::Skip;
+            }
+            // 出力時刻を過ぎた分はまとめて進め、遅れたフレームが連続して出力されないようにする
+            while self.next_sample <= pts {
+                self.next_sample += interval;
+            }
+        }
+        Sample::Emit(pts)
+    }
+}
@@ -0,0 +1,45 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+/// 動画内の時刻・長さ。秒 (2.5) または [HH:]MM:SS[.sss] の形式で指定する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp(pub Duration);
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs_f64();
+        let hours = (secs / 3600.0) as u64;
+        let minutes = (secs / 60.0) as u64 % 60;
+        write!(f, "{:02}:{:02}:{:06.3}", hours, minutes, secs % 60.0)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid time {:?} (expected SECONDS or [HH:]MM:SS[.sss])",
+                s
+            )
+        };
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        if parts.len() > 3 {
+            return Err(invalid());
+        }
+        // 末尾の秒のみ小数を許す
+        let (last, units) = parts.split_last().ok_or_else(invalid)?;
+        // "1:-30" のように秒だけが負になるのを防ぐため、符号は受け付けない
+        if last.starts_with(['-', '+']) {
+            return Err(invalid());
+        }
+        let mut secs = last.parse::<f64>().map_err(|_| invalid())?;
+        for (i, unit) in units.iter().rev().enumerate() {
+            let value = unit.parse::<u64>().map_err(|_| invalid())?;
+            secs += value as f64 * 60f64.powi(i as i32 + 1);
+        }
+        // 負の値・NaN・Duration に収まらない大きな値はエラーにする
+        Duration::try_from_secs_f64(secs)
+            .map(Timestamp)
+            .map_err(|_| invalid())
+    }
+}
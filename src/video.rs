@@ -2,110 +2,389 @@ use ffmpeg_next::frame;
 use ffmpeg_next::media::Type;
 use ffmpeg_next::{self, software::scaling, software::scaling::flag::Flags};
 use image::GrayImage;
+use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crate::args::Args;
+use crate::args::FrameOptions;
 use crate::braille::convert_size;
-use crate::image_processing::{binarize, preprocess_image};
+use crate::dump::FrameDumper;
+use crate::error::{BraillerError, Result};
+use crate::image_processing::{FrameBinarizer, preprocess_image};
 use crate::measure_time;
+use crate::player::{Frame, FrameSource};
 use crate::size::Size;
+use crate::timeline::{Sample, Sampler, frame_interval};
+
+/// デコード・二値化の各段のキューに溜められるフレーム数の上限。
+/// 再生が追いつかない場合はデコードが待たされるため、メモリ使用量はこの数で抑えられる。
+const FRAME_QUEUE_SIZE: usize = 8;
+
+type FrameResult = Result<Frame>;
 
 pub struct VideoData {
-    pub frames: Vec<GrayImage>,
+    pub frames: Frames,
     pub size: Size,
     pub ratio: f32,
     pub fps: f32,
 }
 
-pub fn load_frames(path: &str, args: Args) -> Result<VideoData, Box<dyn std::error::Error>> {
-    let mut ictx = ffmpeg_next::format::input(&path)?;
-    let video_stream_index = ictx
-        .streams()
-        .best(Type::Video)
-        .ok_or("No video stream")?
-        .index();
-
-    let context_decoder = ictx.stream(video_stream_index).ok_or("Invalid stream")?;
-    let codec_params = context_decoder.parameters();
-    let decoder = ffmpeg_next::codec::context::Context::from_parameters(codec_params)?;
-    let mut decoder = decoder.decoder().video()?;
-    // get frame per second
-    let fps = get_fps(&ictx)?;
-
-    let (w, h) = (decoder.width(), decoder.height());
-    let (cols, rows) = convert_size(w, h, args.size.0, args.size.1);
-    let size = Size(cols * 2, rows * 4);
-
-    let mut scaler = scaling::Context::get(
-        decoder.format(),
-        decoder.width(),
-        decoder.height(),
-        ffmpeg_next::format::Pixel::GRAY8,
-        cols * 2,
-        rows * 4,
-        Flags::BILINEAR,
-    )?;
-
-    let mut frames = Vec::new();
-    let mut decoded = frame::Video::empty();
-    let mut first_frame_saved = false;
-
-    for (stream, packet) in ictx.packets() {
-        if stream.index() == video_stream_index {
-            decoder.send_packet(&packet)?;
-
-            while decoder.receive_frame(&mut decoded).is_ok() {
-                let mut gray_frame = frame::Video::empty();
-                scaler.run(&decoded, &mut gray_frame)?;
-
-                let width = gray_frame.width() as usize;
-                let height = gray_frame.height() as usize;
-                let data = gray_frame.data(0);
-                let linesize = data.len() / height;
-                let mut img_buf = Vec::with_capacity(width * height);
-                for y in 0..height {
-                    let start = y * linesize;
-                    let end = start + width;
-                    img_buf.extend_from_slice(&data[start..end]);
+/// 動画ストリームの情報
+#[derive(Debug, Clone, Copy)]
+struct StreamInfo {
+    /// 出力サイズ
+    size: Size,
+    /// 縦横比
+    ratio: f32,
+    /// 出力のフレームレート
+    fps: f32,
+    /// 出力の長さ。コンテナに記録されていない場合は None
+    duration: Option<Duration>,
+}
+
+/// デコードスレッド・二値化スレッドから順に届く処理済みフレームのイテレータ
+pub struct Frames {
+    receiver: Receiver<FrameResult>,
+    workers: Vec<JoinHandle<()>>,
+    /// シーク時にデコードをやり直すための入力と設定
+    path: String,
+    options: FrameOptions,
+    duration: Option<Duration>,
+}
+
+impl Frames {
+    /// 出力の時刻 position の位置からデコードするスレッドを起動し、ストリームの情報が得られるまで待つ
+    fn start(path: &str, options: FrameOptions, position: Duration) -> Result<(Self, StreamInfo)> {
+        let (info_tx, info_rx) = sync_channel(1);
+        let (decoded_tx, decoded_rx) = sync_channel(FRAME_QUEUE_SIZE);
+        let (frame_tx, frame_rx) = sync_channel(FRAME_QUEUE_SIZE);
+
+        // ffmpeg のコンテキストはスレッド間で共有できないため、デコードスレッド内で開く
+        let decode_path = path.to_string();
+        let decode_options = options.clone();
+        let decoder = thread::spawn(move || {
+            match VideoDecoder::open(&decode_path, &decode_options, position) {
+                Ok(mut decoder) => {
+                    if info_tx.send(Ok(decoder.info)).is_err() {
+                        return;
+                    }
+                    if let Err(e) = decoder.run(&decoded_tx) {
+                        let _ = decoded_tx.send(Err(e));
+                    }
                 }
-                // frame to image
-                // let img = GrayImage::from_raw(
-                //     size.0,
-                //     size.1,
-                //     gray_frame.data(0).to_vec()
-                let img = GrayImage::from_raw(width as u32, height as u32, img_buf)
-                    .ok_or("Failed to create image")?;
-
-                // let img = GrayImage::from_raw(cols * 2, rows * 4, gray_frame.data(0).to_vec())
-                //     .ok_or("Failed to create image")?;
-
-                // Save the first frame to tmp.png
-                if !first_frame_saved {
-                    img.save("tmp.png")?;
-                    first_frame_saved = true;
+                Err(e) => {
+                    let _ = info_tx.send(Err(e));
                 }
+            }
+        });
+        let process_options = options.clone();
+        let processor =
+            thread::spawn(move || process_frames(decoded_rx, frame_tx, &process_options));
 
-                let img = measure_time!(preprocess_image(&img, args.contrast, args.invert));
-                // ピクセルを二値化する
-                let img = measure_time!(binarize(&img, args.binarize));
+        let mut frames = Frames {
+            receiver: frame_rx,
+            workers: vec![decoder, processor],
+            path: path.to_string(),
+            options,
+            duration: None,
+        };
+        let info = info_rx.recv().map_err(|_| {
+            BraillerError::Ffmpeg("video decoder exited unexpectedly".to_string())
+        })??;
+        frames.duration = info.duration;
+        Ok((frames, info))
+    }
 
-                frames.push(img);
-            }
+    /// デコードを止め、スレッドの終了を待つ
+    fn stop(&mut self) {
+        // 受信側を先に閉じて、送信待ちのスレッドを終了させる
+        let (_, receiver) = sync_channel(0);
+        drop(std::mem::replace(&mut self.receiver, receiver));
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
         }
     }
+}
+
+impl Iterator for Frames {
+    type Item = FrameResult;
 
-    decoder.send_eof()?;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
 
+impl FrameSource for Frames {
+    /// デコードを止め、pts の位置から開き直す
+    fn seek(&mut self, pts: Duration) -> Result<()> {
+        self.stop();
+        let (frames, _) = Frames::start(&self.path, self.options.clone(), pts)?;
+        *self = frames;
+        Ok(())
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+}
+
+impl Drop for Frames {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// 動画を開き、デコードと二値化をバックグラウンドで行うフレームストリームを返す。
+/// 最初のフレームがデコードされた時点から再生を始められる。
+/// --start, --duration / --end で切り出した範囲を --speed, --fps に合わせたフレーム列になる。
+pub fn load_frames(path: &str, options: FrameOptions) -> Result<VideoData> {
+    let (frames, info) = Frames::start(path, options, Duration::ZERO)?;
     Ok(VideoData {
         frames,
-        size,
-        ratio: w as f32 / h as f32 * 2f32,
-        fps,
+        size: info.size,
+        ratio: info.ratio,
+        fps: info.fps,
     })
 }
 
+/// デコードスレッドが保持する ffmpeg のコンテキスト
+struct VideoDecoder {
+    ictx: ffmpeg_next::format::context::Input,
+    decoder: ffmpeg_next::decoder::Video,
+    scaler: scaling::Context,
+    video_stream_index: usize,
+    /// タイムスタンプの単位（秒）
+    time_base: f64,
+    /// 先頭フレームのタイムスタンプ
+    first_timestamp: Option<i64>,
+    /// 入力の FPS
+    source_fps: f32,
+    /// 送ったフレーム数
+    frame_count: u32,
+    sampler: Sampler,
+    info: StreamInfo,
+}
+
+impl VideoDecoder {
+    /// 動画を開き、出力の時刻 position の位置からデコードできるようにする
+    fn open(path: &str, options: &FrameOptions, position: Duration) -> Result<Self> {
+        let timeline = options.timeline;
+        let ictx = ffmpeg_next::format::input(&path)
+            .map_err(|e| BraillerError::Ffmpeg(format!("{}: {}", path, e)))?;
+        let video_stream_index = ictx
+            .streams()
+            .best(Type::Video)
+            .ok_or_else(|| BraillerError::UnsupportedFormat("no video stream".to_string()))?
+            .index();
+
+        let context_decoder = ictx
+            .stream(video_stream_index)
+            .ok_or_else(|| BraillerError::Ffmpeg("invalid video stream".to_string()))?;
+        let time_base = f64::from(context_decoder.time_base());
+        // シーク後も pts が動画の先頭からの時刻になるよう、ストリームの開始時刻を基準にする
+        let start_time = context_decoder.start_time();
+        let first_timestamp =
+            (start_time != ffmpeg_next::ffi::AV_NOPTS_VALUE).then_some(start_time);
+        let codec_params = context_decoder.parameters();
+        let decoder = ffmpeg_next::codec::context::Context::from_parameters(codec_params)?;
+        let decoder = decoder.decoder().video()?;
+        // get frame per second
+        let fps = get_fps(&ictx)?;
+
+        let (w, h) = (decoder.width(), decoder.height());
+        let (cols, rows) = convert_size(w, h, options.render.size.0, options.render.size.1);
+        let size = Size(cols * 2, rows * 4);
+
+        let scaler = scaling::Context::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            ffmpeg_next::format::Pixel::GRAY8,
+            cols * 2,
+            rows * 4,
+            Flags::BILINEAR,
+        )?;
+
+        // 長さは AV_TIME_BASE (マイクロ秒) 単位
+        let duration = u64::try_from(ictx.duration())
+            .ok()
+            .filter(|&us| us > 0)
+            .map(Duration::from_micros);
+
+        let sampler = Sampler::new(timeline, position);
+        let source_position = sampler.source_position();
+        let mut decoder = Self {
+            ictx,
+            decoder,
+            scaler,
+            video_stream_index,
+            time_base,
+            first_timestamp,
+            source_fps: fps,
+            frame_count: (source_position.as_secs_f32() * fps) as u32,
+            sampler,
+            info: StreamInfo {
+                size,
+                ratio: w as f32 / h as f32 * 2f32,
+                fps: timeline.output_fps(fps)?,
+                duration: timeline.output_duration(duration),
+            },
+        };
+        if !source_position.is_zero() {
+            decoder.seek(source_position)?;
+        }
+        Ok(decoder)
+    }
+
+    /// position の直前のキーフレームに移動する。position より前のフレームは読み飛ばす。
+    fn seek(&mut self, position: Duration) -> Result<()> {
+        let origin = self.first_timestamp.unwrap_or(0) as f64 * self.time_base;
+        let target =
+            ((origin + position.as_secs_f64()) * f64::from(ffmpeg_next::ffi::AV_TIME_BASE)) as i64;
+        self.ictx.seek(target, ..target)?;
+        self.decoder.flush();
+        Ok(())
+    }
+
+    /// 動画をデコードし、出力サイズに縮小したグレースケール画像を順に送る
+    fn run(&mut self, decoded_tx: &SyncSender<FrameResult>) -> Result<()> {
+        let mut images = Vec::new();
+        let mut packet = ffmpeg_next::Packet::empty();
+        loop {
+            match packet.read(&mut self.ictx) {
+                Ok(()) => {}
+                Err(ffmpeg_next::Error::Eof) => break,
+                // 壊れたパケットは読み飛ばす
+                Err(_) => continue,
+            }
+            if packet.stream() != self.video_stream_index {
+                continue;
+            }
+            self.decoder.send_packet(&packet)?;
+            receive_frames(&mut self.decoder, &mut self.scaler, &mut images)?;
+            if !self.send_frames(&mut images, decoded_tx)? {
+                return Ok(());
+            }
+        }
+
+        // デコーダに残っているフレームを取り出す
+        self.decoder.send_eof()?;
+        receive_frames(&mut self.decoder, &mut self.scaler, &mut images)?;
+        self.send_frames(&mut images, decoded_tx)?;
+        Ok(())
+    }
+
+    /// デコードしたフレームのうち、切り出し範囲内で出力のフレームレートに合うものを送る。
+    /// 切り出し終了位置を過ぎた場合は false を返す。
+    fn send_frames(
+        &mut self,
+        images: &mut Vec<(GrayImage, Option<i64>)>,
+        decoded_tx: &SyncSender<FrameResult>,
+    ) -> Result<bool> {
+        for (image, timestamp) in images.drain(..) {
+            let source_pts = self.source_pts(timestamp);
+            let pts = match self.sampler.sample(source_pts) {
+                Sample::Emit(pts) => pts,
+                Sample::Skip => continue,
+                Sample::End => return Ok(false),
+            };
+            // 受信側が閉じられた場合は再生が終了しているのでデコードを止める
+            if decoded_tx.send(Ok(Frame { image, pts })).is_err() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// タイムスタンプを動画の先頭からの時刻に変換する。
+    /// タイムスタンプがない場合はフレーム番号と FPS から求める。
+    fn source_pts(&mut self, timestamp: Option<i64>) -> Duration {
+        let pts = match timestamp {
+            Some(ts) => {
+                let first = *self.first_timestamp.get_or_insert(ts);
+                Duration::from_secs_f64(((ts - first) as f64 * self.time_base).max(0.0))
+            }
+            None => Duration::from_secs_f32(self.frame_count as f32 / self.source_fps),
+        };
+        self.frame_count += 1;
+        pts
+    }
+}
+
+/// デコーダから取り出せるフレームをすべてグレースケール画像に変換し、タイムスタンプと組にする
+fn receive_frames(
+    decoder: &mut ffmpeg_next::decoder::Video,
+    scaler: &mut scaling::Context,
+    images: &mut Vec<(GrayImage, Option<i64>)>,
+) -> Result<()> {
+    let mut decoded = frame::Video::empty();
+    while decoder.receive_frame(&mut decoded).is_ok() {
+        let mut gray_frame = frame::Video::empty();
+        scaler.run(&decoded, &mut gray_frame)?;
+
+        let width = gray_frame.width() as usize;
+        let height = gray_frame.height() as usize;
+        let data = gray_frame.data(0);
+        let linesize = data.len() / height;
+        let mut img_buf = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let start = y * linesize;
+            let end = start + width;
+            img_buf.extend_from_slice(&data[start..end]);
+        }
+        // frame to image
+        let img = GrayImage::from_raw(width as u32, height as u32, img_buf)
+            .ok_or_else(|| BraillerError::Ffmpeg("unexpected frame buffer size".to_string()))?;
+        images.push((img, decoded.timestamp()));
+    }
+    Ok(())
+}
+
+/// デコード済みフレームを前処理・二値化して送る
+fn process_frames(
+    decoded_rx: Receiver<FrameResult>,
+    frame_tx: SyncSender<FrameResult>,
+    options: &FrameOptions,
+) {
+    let mut binarizer = FrameBinarizer::new(options.render.binarize);
+    let mut dumper = match FrameDumper::new(&options.dump_frames, options.dump_every) {
+        Ok(dumper) => dumper,
+        Err(e) => {
+            let _ = frame_tx.send(Err(e));
+            return;
+        }
+    };
+    for frame in decoded_rx {
+        let frame = frame.and_then(|frame| {
+            let img = measure_time!(preprocess_image(
+                &frame.image,
+                options.render.contrast,
+                options.render.invert
+            ));
+            // ピクセルを二値化する
+            let image = measure_time!(binarizer.binarize(&img));
+            if let Some(dumper) = &mut dumper {
+                dumper.dump(&img, &image)?;
+            }
+            Ok(Frame {
+                image,
+                pts: frame.pts,
+            })
+        });
+        if frame_tx.send(frame).is_err() {
+            break;
+        }
+    }
+}
+
 /// Get the FPS of a video file
-pub fn get_fps(ictx: &ffmpeg_next::format::context::Input) -> Result<f32, String> {
-    let video_stream = ictx.streams().best(Type::Video).ok_or("No video stream")?;
+pub fn get_fps(ictx: &ffmpeg_next::format::context::Input) -> Result<f32> {
+    let video_stream = ictx
+        .streams()
+        .best(Type::Video)
+        .ok_or_else(|| BraillerError::UnsupportedFormat("no video stream".to_string()))?;
     let avg_frame_rate = video_stream.avg_frame_rate();
-    Ok(avg_frame_rate.0 as f32 / avg_frame_rate.1 as f32)
+    let fps = avg_frame_rate.0 as f32 / avg_frame_rate.1 as f32;
+    frame_interval(fps)?;
+    Ok(fps)
 }
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use brailler::GrayImage;
+use brailler::batch::{expand_inputs, output_path, output_paths, wildcard_match};
+use brailler::error::BraillerError;
+use image::Luma;
+
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    wildcard_match(&pattern, &name)
+}
+
+#[test]
+fn wildcard_match_literal() {
+    assert!(matches("a.png", "a.png"));
+    assert!(!matches("a.png", "b.png"));
+    assert!(!matches("a.png", "a.png.bak"));
+    assert!(matches("", ""));
+    assert!(!matches("", "a"));
+}
+
+#[test]
+fn wildcard_match_star() {
+    assert!(matches("*.png", "photo.png"));
+    assert!(matches("*.png", ".png"));
+    assert!(!matches("*.png", "photo.jpg"));
+    assert!(matches("img*", "img"));
+    assert!(matches("a*b*c", "aXXbYc"));
+    assert!(!matches("a*b*c", "aXXcYb"));
+    assert!(matches("*", "日本語.png"));
+}
+
+#[test]
+fn wildcard_match_question() {
+    assert!(matches("img?.png", "img1.png"));
+    assert!(!matches("img?.png", "img.png"));
+    assert!(!matches("img?.png", "img12.png"));
+    // ? は1バイトではなく1文字に一致する
+    assert!(matches("?.png", "画.png"));
+}
+
+#[test]
+fn output_path_template() {
+    let input = Path::new("photos/cat.png");
+    assert_eq!(
+        output_path("{stem}.txt", input, "out"),
+        PathBuf::from("out/cat.txt")
+    );
+    assert_eq!(
+        output_path("{name}.html", input, "out"),
+        PathBuf::from("out/cat.png.html")
+    );
+    assert_eq!(
+        output_path("braille-{stem}-{stem}.svg", input, "out"),
+        PathBuf::from("out/braille-cat-cat.svg")
+    );
+}
+
+#[test]
+fn output_paths_rejects_duplicates() {
+    let inputs = vec![PathBuf::from("a/cat.png"), PathBuf::from("b/cat.jpg")];
+    let result = output_paths("{stem}.txt", &inputs, "out");
+    assert!(matches!(result, Err(BraillerError::InvalidArgument(_))));
+
+    // {name} を使えば拡張子で区別される
+    let outputs = output_paths("{name}.txt", &inputs, "out").unwrap();
+    assert_eq!(
+        outputs,
+        vec![
+            PathBuf::from("out/cat.png.txt"),
+            PathBuf::from("out/cat.jpg.txt")
+        ]
+    );
+}
+
+#[test]
+fn expand_inputs_glob_and_directory() {
+    let dir = std::env::temp_dir().join(format!("brailler-test-{}-batch", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let image = GrayImage::from_pixel(2, 4, Luma([0]));
+    image.save(dir.join("b.png")).unwrap();
+    image.save(dir.join("a.png")).unwrap();
+    image.save(dir.join("c.bmp")).unwrap();
+    // 画像ではないファイルはディレクトリ指定でも除外される
+    fs::write(dir.join("notes.png.txt"), "not an image").unwrap();
+
+    let dir_name = dir.to_string_lossy().into_owned();
+    let all = expand_inputs(std::slice::from_ref(&dir_name)).unwrap();
+    let glob = expand_inputs(&[format!("{}/*.png", dir_name)]).unwrap();
+    let _ = fs::remove_dir_all(&dir);
+
+    assert_eq!(
+        all,
+        vec![dir.join("a.png"), dir.join("b.png"), dir.join("c.bmp")]
+    );
+    assert_eq!(glob, vec![dir.join("a.png"), dir.join("b.png")]);
+}
+
+#[test]
+fn expand_inputs_rejects_stdin_and_directory_globs() {
+    assert!(matches!(
+        expand_inputs(&["-".to_string()]),
+        Err(BraillerError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        expand_inputs(&["*/a.png".to_string()]),
+        Err(BraillerError::InvalidArgument(_))
+    ));
+}
@@ -0,0 +1,63 @@
+use brailler::GrayImage;
+use brailler::braille::generate_braille;
+use brailler::dot_canvas::DotCanvas;
+use image::Luma;
+
+const BLANK: char = '\u{2800}';
+const FULL: char = '\u{28FF}';
+
+#[test]
+fn generate_braille_exact_size() {
+    let img = GrayImage::from_pixel(4, 8, Luma([0]));
+    let output = generate_braille(&img, 2, 2).unwrap();
+    assert_eq!(output, format!("{FULL}{FULL}\n{FULL}{FULL}\n"));
+}
+
+#[test]
+fn generate_braille_pads_partial_cells() {
+    // 3x5 の画像は 2x2 セルに収まり、はみ出したドットは off になる
+    let img = GrayImage::from_pixel(3, 5, Luma([0]));
+    let output = generate_braille(&img, 2, 2).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    // 右端のセルは左列のみ on
+    assert_eq!(lines[0], format!("{FULL}\u{2847}"));
+    // 下端のセルは 1 行目のみ on
+    assert_eq!(lines[1], "\u{2809}\u{2801}");
+}
+
+#[test]
+fn generate_braille_rejects_oversized_grid() {
+    let img = GrayImage::from_pixel(4, 8, Luma([0]));
+    assert!(generate_braille(&img, 3, 2).is_err());
+    assert!(generate_braille(&img, 2, 3).is_err());
+}
+
+#[test]
+fn generate_braille_empty_image() {
+    let img = GrayImage::new(0, 0);
+    assert_eq!(generate_braille(&img, 0, 0).unwrap(), "");
+    assert!(generate_braille(&img, 1, 1).is_err());
+}
+
+#[test]
+fn to_braille_pads_partial_cells() {
+    let mut canvas = DotCanvas::new(3, 5);
+    canvas.set(2, 0, 1);
+    canvas.set(0, 4, 1);
+    let output = canvas.to_braille().unwrap();
+    assert_eq!(output, format!("{BLANK}\u{2801}\n\u{2801}{BLANK}\n"));
+}
+
+#[test]
+fn to_braille_exact_size() {
+    let mut canvas = DotCanvas::new(2, 4);
+    canvas.set(1, 3, 1);
+    assert_eq!(canvas.to_braille().unwrap(), "\u{2880}\n");
+}
+
+#[test]
+fn to_braille_rejects_empty_canvas() {
+    assert!(DotCanvas::new(0, 4).to_braille().is_err());
+    assert!(DotCanvas::new(2, 0).to_braille().is_err());
+}
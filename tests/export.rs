@@ -0,0 +1,180 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::time::Duration;
+
+use brailler::error::BraillerError;
+use brailler::export::{
+    DOT_PITCH, OutputFormat, TextFrame, json_string, save_gif, to_ansi, to_asciicast, to_html,
+    to_svg,
+};
+use image::AnimationDecoder;
+use image::codecs::gif::GifDecoder;
+
+fn frame(text: &str, millis: u64) -> TextFrame {
+    TextFrame {
+        pts: Duration::from_millis(millis),
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn output_format_from_extension() {
+    for (path, format) in [
+        ("out.txt", OutputFormat::Text),
+        ("out.html", OutputFormat::Html),
+        ("out.htm", OutputFormat::Html),
+        ("out.svg", OutputFormat::Svg),
+        ("out.png", OutputFormat::Png),
+        ("out.ans", OutputFormat::Ansi),
+        ("out.sh", OutputFormat::Script),
+        ("out.fish", OutputFormat::Script),
+        ("out.ps1", OutputFormat::Script),
+        ("out.py", OutputFormat::Script),
+        ("out.js", OutputFormat::Script),
+        ("out.cjs", OutputFormat::Script),
+        ("out.cast", OutputFormat::Asciicast),
+        ("out.gif", OutputFormat::Gif),
+        // 大文字の拡張子
+        ("OUT.TXT", OutputFormat::Text),
+        ("dir.d/Out.Gif", OutputFormat::Gif),
+    ] {
+        assert_eq!(OutputFormat::from_path(path).unwrap(), format, "{}", path);
+    }
+}
+
+#[test]
+fn output_format_unknown_extension() {
+    for path in ["out.jpg", "out", "out.txt.bak", ""] {
+        assert!(
+            matches!(
+                OutputFormat::from_path(path),
+                Err(BraillerError::UnsupportedFormat(_))
+            ),
+            "{:?} should be rejected",
+            path
+        );
+    }
+}
+
+#[test]
+fn output_format_resolve_prefers_format() {
+    assert_eq!(
+        OutputFormat::resolve(Some(OutputFormat::Svg), "out.txt").unwrap(),
+        OutputFormat::Svg
+    );
+    assert_eq!(
+        OutputFormat::resolve(None, "out.txt").unwrap(),
+        OutputFormat::Text
+    );
+}
+
+#[test]
+fn json_string_escapes() {
+    assert_eq!(json_string("⣿ plain"), "\"⣿ plain\"");
+    assert_eq!(json_string("\"\\"), r#""\"\\""#);
+    assert_eq!(json_string("a\nb\r\tc"), r#""a\nb\r\tc""#);
+    assert_eq!(json_string("\x1B[2J\x00\x7F"), r#""\u001b[2J\u0000\u007f""#);
+}
+
+#[test]
+fn asciicast_header_and_events() {
+    let cast = to_asciicast(&[
+        frame("⠁⠂\n⠄⡀\n", 0),
+        frame("⠁⠂\n⠄⡀\n", 100),
+        frame("⠁⣿\n⠄⡀\n", 1500),
+    ]);
+    let lines: Vec<&str> = cast.lines().collect();
+    // 最初の行は JSON のヘッダー。高さはカーソルの行を含む
+    assert!(lines[0].starts_with("{\"version\": 2, \"width\": 2, \"height\": 3, \"timestamp\": "));
+    assert!(lines[0].ends_with(", \"title\": \"brailler\"}"));
+    // 変化のないフレームはイベントにならない
+    assert_eq!(
+        lines[1..],
+        [
+            r#"[0.000000, "o", "\u001b[2J\u001b[1;1H⠁⠂\r\n⠄⡀\r\n\u001b[3;1H"]"#,
+            r#"[1.500000, "o", "\u001b[1;2H⣿\u001b[3;1H"]"#,
+        ]
+    );
+}
+
+#[test]
+fn gif_frame_delays() {
+    let path = std::env::temp_dir().join(format!("brailler-test-{}.gif", std::process::id()));
+    let frames = [
+        frame("⠁\n", 0),
+        frame("⠂\n", 125),
+        frame("⠄\n", 250),
+        frame("⡀\n", 1000),
+    ];
+    save_gif(&frames, 4.0, &path.to_string_lossy()).unwrap();
+    let decoder = GifDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+    let delays: Vec<u32> = decoder
+        .into_frames()
+        .map(|frame| {
+            let (numer, denom) = frame.unwrap().delay().numer_denom_ms();
+            numer / denom
+        })
+        .collect();
+    let _ = fs::remove_file(&path);
+    // 10ms 単位に丸めた pts の差になり、誤差は蓄積しない。最後のフレームは 1/fps 秒
+    assert_eq!(delays, [130, 120, 750, 250]);
+}
+
+#[test]
+fn gif_rejects_invalid_fps() {
+    let path = std::env::temp_dir().join(format!("brailler-test-{}-nan.gif", std::process::id()));
+    let result = save_gif(&[frame("⠁\n", 0)], f32::NAN, &path.to_string_lossy());
+    let _ = fs::remove_file(&path);
+    assert!(matches!(result, Err(BraillerError::Decode(_))));
+}
+
+#[test]
+fn svg_structure() {
+    // ⠁ は左上、⢀ は右下のドット
+    let svg = to_svg("⠁⢀\n");
+    let (width, height) = (2 * 2 * DOT_PITCH, 4 * DOT_PITCH);
+    assert!(svg.starts_with(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+    )));
+    assert!(svg.ends_with("</g>\n</svg>\n"));
+    let circles: Vec<&str> = svg
+        .lines()
+        .filter(|line| line.starts_with("<circle"))
+        .collect();
+    assert_eq!(
+        circles,
+        [
+            "<circle cx=\"2\" cy=\"2\" r=\"1.5\"/>",
+            "<circle cx=\"14\" cy=\"14\" r=\"1.5\"/>",
+        ]
+    );
+}
+
+#[test]
+fn svg_ignores_non_braille_text() {
+    // Braille 以外の文字は空セルになり、SVG にそのまま埋め込まれない
+    let svg = to_svg("<a>&\n");
+    assert!(!svg.contains("<a>"));
+    assert!(!svg.contains('&'));
+    assert!(!svg.contains("<circle"));
+    assert!(svg.contains("width=\"32\" height=\"16\""));
+}
+
+#[test]
+fn html_structure_and_escaping() {
+    let html = to_html("⣿⠀\n<b>Tom & Jerry</b>\n");
+    assert!(html.starts_with("<!DOCTYPE html>\n<html>\n"));
+    assert!(html.contains("<meta charset=\"utf-8\">"));
+    assert!(html.contains(">\n⣿⠀\n&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;\n</pre>\n"));
+    assert!(html.ends_with("</body>\n</html>\n"));
+}
+
+#[test]
+fn ansi_draws_first_frame_and_changes() {
+    let frames = ["⠁⠂\n".to_string(), "⠁⠂\n".to_string(), "⠁⣿\n".to_string()];
+    assert_eq!(
+        to_ansi(&frames),
+        "\x1B[2J\x1B[1;1H⠁⠂\n\x1B[2;1H\x1B[1;2H⣿\x1B[2;1H"
+    );
+    assert_eq!(to_ansi(&[]), "");
+}
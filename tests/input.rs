@@ -0,0 +1,25 @@
+use std::fs;
+use std::path::Path;
+
+use brailler::input::{STDIN_PATH, file_path};
+
+#[test]
+fn file_path_writes_stdin_to_temp_file() {
+    let (path, temp) = file_path(STDIN_PATH, Some(b"video data")).unwrap();
+    assert!(temp.is_some());
+    assert_eq!(fs::read(&path).unwrap(), b"video data");
+    // 一時ファイルは TempInput の Drop で削除される
+    drop(temp);
+    assert!(!Path::new(&path).exists());
+}
+
+#[test]
+fn file_path_keeps_file_paths() {
+    let (path, temp) = file_path("movie.mp4", None).unwrap();
+    assert_eq!(path, "movie.mp4");
+    assert!(temp.is_none());
+    // ファイルの場合は読み込み済みのデータがあっても書き出さない
+    let (path, temp) = file_path("movie.mp4", Some(b"video data")).unwrap();
+    assert_eq!(path, "movie.mp4");
+    assert!(temp.is_none());
+}
@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use brailler::player::{FrameAction, PlaybackClock};
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn position_follows_elapsed_time() {
+    let start = Instant::now();
+    let clock = PlaybackClock::new_at(10.0, ms(500), start).unwrap();
+    assert_eq!(clock.position_at(start), ms(500));
+    assert_eq!(clock.position_at(start + ms(250)), ms(750));
+    // 開始前の時刻でも戻らない
+    let clock = PlaybackClock::new_at(10.0, ms(500), start + ms(100)).unwrap();
+    assert_eq!(clock.position_at(start), ms(500));
+}
+
+#[test]
+fn clock_rejects_invalid_fps() {
+    for fps in [0.0, f32::NAN] {
+        assert!(PlaybackClock::new_at(fps, Duration::ZERO, Instant::now()).is_err());
+    }
+}
+
+#[test]
+fn schedule_drops_frames_after_the_next_frame_time() {
+    let start = Instant::now();
+    // 8fps: フレーム間隔は 125ms
+    let clock = PlaybackClock::new_at(8.0, Duration::ZERO, start).unwrap();
+    assert_eq!(clock.schedule_at(ms(0), start), FrameAction::Show);
+    assert_eq!(clock.schedule_at(ms(250), start), FrameAction::Show);
+    // 表示時刻を過ぎていても、次のフレームの時刻までは表示する
+    assert_eq!(clock.schedule_at(ms(0), start + ms(124)), FrameAction::Show);
+    assert_eq!(clock.schedule_at(ms(0), start + ms(125)), FrameAction::Drop);
+    assert_eq!(
+        clock.schedule_at(ms(125), start + ms(200)),
+        FrameAction::Show
+    );
+    assert_eq!(
+        clock.schedule_at(ms(125), start + ms(250)),
+        FrameAction::Drop
+    );
+}
+
+#[test]
+fn time_until_frame() {
+    let start = Instant::now();
+    let mut clock = PlaybackClock::new_at(10.0, Duration::ZERO, start).unwrap();
+    assert_eq!(clock.time_until_at(ms(300), start + ms(100)), ms(200));
+    assert_eq!(
+        clock.time_until_at(ms(300), start + ms(400)),
+        Duration::ZERO
+    );
+
+    // 2倍速では実時間の待ち時間は半分になる
+    clock.set_speed_at(2.0, start + ms(100));
+    assert_eq!(clock.speed(), 2.0);
+    assert_eq!(clock.time_until_at(ms(300), start + ms(100)), ms(100));
+    assert_eq!(clock.position_at(start + ms(150)), ms(200));
+}
+
+#[test]
+fn pause_stops_the_clock() {
+    let start = Instant::now();
+    let mut clock = PlaybackClock::new_at(10.0, Duration::ZERO, start).unwrap();
+    clock.set_paused_at(true, start + ms(300));
+    assert!(clock.is_paused());
+    assert_eq!(clock.position_at(start + ms(5000)), ms(300));
+    // 一時停止中はフレームを捨てない
+    assert_eq!(
+        clock.schedule_at(ms(300), start + ms(5000)),
+        FrameAction::Show
+    );
+
+    // 再開した時刻から進む
+    clock.set_paused_at(false, start + ms(5000));
+    assert_eq!(clock.position_at(start + ms(5100)), ms(400));
+}
+
+#[test]
+fn seek_moves_the_position() {
+    let start = Instant::now();
+    let mut clock = PlaybackClock::new_at(10.0, Duration::ZERO, start).unwrap();
+    clock.seek_at(ms(10_000), start + ms(500));
+    assert_eq!(clock.position_at(start + ms(500)), ms(10_000));
+    assert_eq!(clock.position_at(start + ms(600)), ms(10_100));
+    assert_eq!(
+        clock.schedule_at(ms(500), start + ms(500)),
+        FrameAction::Drop
+    );
+    assert_eq!(
+        clock.schedule_at(ms(10_000), start + ms(500)),
+        FrameAction::Show
+    );
+}
@@ -0,0 +1,619 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use brailler::error::BraillerError;
+use brailler::export::TextFrame;
+use brailler::inspect::extract_frames;
+use brailler::scriptify::{
+    FRAME_SEPARATOR, ScriptCompression, ScriptOptions, ScriptShell, ScriptSource, decode_frames,
+    encode_frames, generate_script_for_image, generate_script_for_video, read_metadata,
+};
+use clap::ValueEnum;
+
+const IMAGE: &str = "\u{28FF}\u{2800}\u{28FF}\n\u{2800}\u{28FF}\u{2800}\n";
+// 字幕の行のように "," を含むフレームも区切りと誤認しない
+const FRAMES: [(u64, &str); 3] = [
+    (0, "\u{28FF}\u{2800}\n\u{2800}\u{2800}\n"),
+    (100, "\u{2800}\u{28FF}\n,\n"),
+    (300, "\u{2800}\u{2800}\nHello, world\n"),
+];
+const FPS: f32 = 10.0;
+
+fn text_frames() -> Vec<TextFrame> {
+    FRAMES
+        .iter()
+        .map(|&(millis, text)| TextFrame {
+            pts: Duration::from_millis(millis),
+            text: text.to_string(),
+        })
+        .collect()
+}
+
+/// 1つのドットが 4x3 のグリッドを移動するフレーム
+fn moving_dot_frames() -> Vec<TextFrame> {
+    (0..6)
+        .map(|i| TextFrame {
+            pts: Duration::from_millis(i as u64 * 100),
+            text: (0..3)
+                .map(|y| {
+                    let line: String = (0..4)
+                        .map(|x| {
+                            if y * 4 + x == i {
+                                '\u{28FF}'
+                            } else {
+                                '\u{2800}'
+                            }
+                        })
+                        .collect();
+                    line + "\n"
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// interpreter がインストールされていない場合のエラーメッセージ。
+/// sh, dash, bash は必須。python3, node, fish, pwsh, xz, zstd を使うテストは既定で無視し、
+/// --ignored で明示的に実行した場合は、何も確認せずに成功しないよう失敗させる
+fn not_installed(interpreter: &str) -> String {
+    format!(
+        "{} is not installed; install it to run this test",
+        interpreter
+    )
+}
+
+/// スクリプトを一時ファイルに書き出して interpreter で実行し、標準出力を返す。
+/// interpreter が見つからない場合は失敗する
+fn run_script(interpreter: &str, name: &str, script: &str) -> String {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("brailler-test-{}-{}", std::process::id(), name));
+    fs::write(&path, script).unwrap();
+    let result = Command::new(interpreter).arg(&path).output();
+    fs::remove_file(&path).unwrap();
+    match result {
+        Ok(output) => {
+            assert!(
+                output.status.success(),
+                "{} failed: {}",
+                interpreter,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            String::from_utf8(output.stdout).unwrap()
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => panic!("{}", not_installed(interpreter)),
+        Err(e) => panic!("failed to run {}: {}", interpreter, e),
+    }
+}
+
+/// 画面を模擬し、出力のカーソル移動・画面消去・文字を反映する
+fn apply(screen: &mut Vec<Vec<char>>, output: &str) {
+    let (mut row, mut col) = (0, 0);
+    let mut chars = output.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                assert_eq!(chars.next(), Some('['));
+                let params: String = chars
+                    .by_ref()
+                    .take_while(|&c| c != 'H' && c != 'J')
+                    .collect();
+                if params == "2" {
+                    screen.clear();
+                    continue;
+                }
+                let mut position = params.split(';').map(|p| p.parse().unwrap_or(1));
+                row = position.next().unwrap_or(1) - 1;
+                col = position.next().unwrap_or(1) - 1;
+            }
+            '\n' => (row, col) = (row + 1, 0),
+            c => {
+                if screen.len() <= row {
+                    screen.resize(row + 1, Vec::new());
+                }
+                if screen[row].len() <= col {
+                    screen[row].resize(col + 1, ' ');
+                }
+                screen[row][col] = c;
+                col += 1;
+            }
+        }
+    }
+}
+
+/// 動画スクリプトの出力は、画面の消去とペイロードの各フレームの内容になる
+fn expected_output(payload: &str) -> String {
+    let frames: String = payload
+        .split_inclusive('\n')
+        .filter(|line| !line.starts_with(FRAME_SEPARATOR))
+        .collect();
+    format!("\x1b[2J{}", frames)
+}
+
+fn assert_image_script(shell: ScriptShell, interpreter: &str) {
+    let options = ScriptOptions {
+        shell,
+        ..Default::default()
+    };
+    let script = generate_script_for_image(IMAGE, &options).unwrap();
+    let output = run_script(interpreter, &format!("image-{}", interpreter), &script);
+    assert_eq!(output, IMAGE);
+}
+
+fn assert_video_script(shell: ScriptShell, interpreter: &str) {
+    for (frames, keyframe_interval) in [(text_frames(), None), (moving_dot_frames(), Some(4))] {
+        let options = ScriptOptions {
+            shell,
+            keyframe_interval,
+            ..Default::default()
+        };
+        let script = generate_script_for_video(&frames, FPS, &options).unwrap();
+        let started = Instant::now();
+        let output = run_script(interpreter, &format!("video-{}", interpreter), &script);
+        assert_eq!(
+            output,
+            expected_output(&encode_frames(&frames, FPS, &options).unwrap())
+        );
+        // 各フレームの表示時間の合計 (最後のフレームは 1/fps 秒)
+        let total = frames.last().unwrap().pts + Duration::from_secs_f32(1.0 / FPS);
+        assert!(started.elapsed() >= total);
+    }
+}
+
+#[test]
+fn encode_frames_with_delays() {
+    let payload = encode_frames(&text_frames(), FPS, &ScriptOptions::default()).unwrap();
+    // 各フレームはカーソルを左上に戻してから表示する
+    assert_eq!(
+        payload,
+        "\x0c0.100\n\x1b[H\u{28FF}\u{2800}\n\u{2800}\u{2800}\n\
+         \x0c0.200\n\x1b[H\u{2800}\u{28FF}\n,\n\
+         \x0c0.100\n\x1b[H\u{2800}\u{2800}\nHello, world\n"
+    );
+}
+
+#[test]
+fn encode_frames_strips_separator() {
+    let frames = [TextFrame {
+        pts: Duration::ZERO,
+        text: "a\x0cb".to_string(),
+    }];
+    assert_eq!(
+        encode_frames(&frames, 2.0, &ScriptOptions::default()).unwrap(),
+        "\x0c0.500\n\x1b[Hab\n"
+    );
+}
+
+#[test]
+fn encode_frames_rejects_invalid_fps() {
+    for fps in [0.0, f32::NAN] {
+        assert!(encode_frames(&text_frames(), fps, &ScriptOptions::default()).is_err());
+    }
+}
+
+#[test]
+fn encode_frames_with_delta() {
+    let frames = moving_dot_frames();
+    let options = ScriptOptions {
+        keyframe_interval: Some(4),
+        ..Default::default()
+    };
+    let payload = encode_frames(&frames, FPS, &options).unwrap();
+    let full = encode_frames(&frames, FPS, &ScriptOptions::default()).unwrap();
+    assert!(payload.len() < full.len());
+
+    let mut screen = Vec::new();
+    let bodies = payload.split(FRAME_SEPARATOR).skip(1);
+    for (i, (body, frame)) in bodies.zip(&frames).enumerate() {
+        let (_, body) = body.split_once('\n').unwrap();
+        // キーフレームのみフレーム全体を含む
+        assert_eq!(body.starts_with("\x1b[H"), i % 4 == 0, "frame {}", i);
+        apply(&mut screen, body);
+        let lines: Vec<String> = screen.iter().map(|line| line.iter().collect()).collect();
+        assert_eq!(lines.join("\n") + "\n", frame.text, "frame {}", i);
+    }
+}
+
+#[test]
+fn encode_frames_with_delta_on_shape_change() {
+    // 桁数が変わるフレームは差分にできないため全体を保存する
+    let frames = text_frames();
+    let options = ScriptOptions {
+        keyframe_interval: Some(10),
+        ..Default::default()
+    };
+    assert_eq!(
+        encode_frames(&frames, FPS, &options).unwrap(),
+        encode_frames(&frames, FPS, &ScriptOptions::default()).unwrap()
+    );
+}
+
+#[test]
+fn sh_image_script() {
+    assert_image_script(ScriptShell::Sh, "sh");
+    assert_image_script(ScriptShell::Sh, "dash");
+    assert_image_script(ScriptShell::Sh, "bash");
+}
+
+#[test]
+fn sh_video_script() {
+    assert_video_script(ScriptShell::Sh, "sh");
+    assert_video_script(ScriptShell::Sh, "dash");
+    assert_video_script(ScriptShell::Sh, "bash");
+}
+
+#[test]
+fn bash_image_script() {
+    assert_image_script(ScriptShell::Bash, "bash");
+}
+
+#[test]
+fn bash_video_script() {
+    assert_video_script(ScriptShell::Bash, "bash");
+}
+
+#[test]
+#[ignore = "requires fish"]
+fn fish_scripts() {
+    assert_image_script(ScriptShell::Fish, "fish");
+    assert_video_script(ScriptShell::Fish, "fish");
+}
+
+#[test]
+#[ignore = "requires pwsh"]
+fn powershell_scripts() {
+    assert_image_script(ScriptShell::Powershell, "pwsh");
+    assert_video_script(ScriptShell::Powershell, "pwsh");
+}
+
+/// 2回ループするスクリプトが、各フレームを2回表示してから端末を戻すことを確認する
+fn assert_loops_and_restores(shell: ScriptShell, interpreter: &str) {
+    let frames = text_frames();
+    let options = ScriptOptions {
+        shell,
+        loops: 2,
+        hide_cursor: true,
+        alt_screen: true,
+        ..Default::default()
+    };
+    let script = generate_script_for_video(&frames, FPS, &options).unwrap();
+    let output = run_script(interpreter, &format!("loop-{}", interpreter), &script);
+    let played = expected_output(&encode_frames(&frames, FPS, &options).unwrap());
+    let played = played.strip_prefix("\x1b[2J").unwrap();
+    assert_eq!(
+        output,
+        format!(
+            "\x1b[?1049h\x1b[?25l\x1b[2J{}{}\x1b[?25h\x1b[?1049l",
+            played, played
+        ),
+        "{}",
+        interpreter
+    );
+}
+
+#[test]
+fn video_script_loops_and_restores_terminal() {
+    assert_loops_and_restores(ScriptShell::Sh, "sh");
+    assert_loops_and_restores(ScriptShell::Sh, "dash");
+    assert_loops_and_restores(ScriptShell::Bash, "bash");
+}
+
+/// 無限ループのスクリプトに Ctrl-C を送ると、端末を戻して終了する
+fn assert_restores_on_interrupt(shell: ScriptShell, interpreter: &str) {
+    use std::os::unix::process::CommandExt;
+    use std::process::Stdio;
+
+    let options = ScriptOptions {
+        shell,
+        loops: 0,
+        hide_cursor: true,
+        ..Default::default()
+    };
+    let script = generate_script_for_video(&text_frames(), FPS, &options).unwrap();
+    let path = std::env::temp_dir().join(format!(
+        "brailler-test-{}-interrupt-{}",
+        std::process::id(),
+        interpreter
+    ));
+    fs::write(&path, script).unwrap();
+    // 端末の Ctrl-C と同様に、プロセスグループ全体に SIGINT を送る
+    let child = Command::new(interpreter)
+        .arg(&path)
+        .stdout(Stdio::piped())
+        .process_group(0)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            fs::remove_file(&path).unwrap();
+            panic!("{}", not_installed(interpreter));
+        }
+        Err(e) => panic!("failed to run {}: {}", interpreter, e),
+    };
+    std::thread::sleep(Duration::from_millis(500));
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGINT);
+    }
+    let output = child.wait_with_output().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(130), "{}", interpreter);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("\x1b[?25l\x1b[2J"), "{}", interpreter);
+    assert!(stdout.ends_with("\x1b[?25h"), "{}", interpreter);
+}
+
+#[test]
+fn video_script_restores_terminal_on_interrupt() {
+    assert_restores_on_interrupt(ScriptShell::Bash, "bash");
+}
+
+/// compression で圧縮したスクリプトが、圧縮しない場合と同じ出力になることを確認する
+fn assert_compressed_scripts(
+    shell: ScriptShell,
+    interpreter: &str,
+    compression: ScriptCompression,
+) {
+    let options = ScriptOptions {
+        shell,
+        compression,
+        compression_level: Some(1),
+        ..Default::default()
+    };
+    let name = format!("compression-{:?}-{}", compression, interpreter);
+    let script = generate_script_for_image(IMAGE, &options).unwrap();
+    let output = run_script(interpreter, &format!("image-{}", name), &script);
+    assert_eq!(output, IMAGE, "{}", name);
+    let frames = text_frames();
+    let script = generate_script_for_video(&frames, FPS, &options).unwrap();
+    let output = run_script(interpreter, &format!("video-{}", name), &script);
+    assert_eq!(
+        output,
+        expected_output(&encode_frames(&frames, FPS, &options).unwrap()),
+        "{}",
+        name
+    );
+}
+
+#[test]
+fn scripts_with_compression() {
+    for compression in [ScriptCompression::None, ScriptCompression::Gzip] {
+        assert_compressed_scripts(ScriptShell::Sh, "dash", compression);
+        assert_compressed_scripts(ScriptShell::Bash, "bash", compression);
+    }
+}
+
+#[test]
+#[ignore = "requires xz, zstd, python3 and node"]
+fn scripts_with_xz_and_zstd() {
+    for compression in [ScriptCompression::Xz, ScriptCompression::Zstd] {
+        assert_compressed_scripts(ScriptShell::Sh, "dash", compression);
+        assert_compressed_scripts(ScriptShell::Bash, "bash", compression);
+        assert_compressed_scripts(ScriptShell::Python, "python3", compression);
+        assert_compressed_scripts(ScriptShell::Node, "node", compression);
+    }
+}
+
+#[test]
+#[ignore = "requires python3"]
+fn python_scripts() {
+    assert_image_script(ScriptShell::Python, "python3");
+    assert_video_script(ScriptShell::Python, "python3");
+    assert_loops_and_restores(ScriptShell::Python, "python3");
+    assert_restores_on_interrupt(ScriptShell::Python, "python3");
+    for compression in [ScriptCompression::None, ScriptCompression::Gzip] {
+        assert_compressed_scripts(ScriptShell::Python, "python3", compression);
+    }
+}
+
+#[test]
+#[ignore = "requires node"]
+fn node_scripts() {
+    assert_image_script(ScriptShell::Node, "node");
+    assert_video_script(ScriptShell::Node, "node");
+    assert_loops_and_restores(ScriptShell::Node, "node");
+    assert_restores_on_interrupt(ScriptShell::Node, "node");
+    for compression in [ScriptCompression::None, ScriptCompression::Gzip] {
+        assert_compressed_scripts(ScriptShell::Node, "node", compression);
+    }
+    assert_missing_decompressor(ScriptShell::Node, "node", ScriptCompression::Xz, "xz");
+}
+
+#[test]
+fn script_shell_from_path() {
+    assert_eq!(ScriptShell::from_path("play.sh"), ScriptShell::Bash);
+    assert_eq!(ScriptShell::from_path("play"), ScriptShell::Bash);
+    assert_eq!(ScriptShell::from_path("play.fish"), ScriptShell::Fish);
+    assert_eq!(ScriptShell::from_path("play.PS1"), ScriptShell::Powershell);
+    assert_eq!(ScriptShell::from_path("play.py"), ScriptShell::Python);
+    assert_eq!(ScriptShell::from_path("play.js"), ScriptShell::Node);
+}
+
+#[test]
+fn compression_level_out_of_range() {
+    let options = ScriptOptions {
+        compression: ScriptCompression::Zstd,
+        compression_level: Some(23),
+        ..Default::default()
+    };
+    assert!(generate_script_for_image(IMAGE, &options).is_err());
+}
+
+/// 展開コマンドが見つからない場合は、インストールを促すメッセージを表示して終了する
+fn assert_missing_decompressor(
+    shell: ScriptShell,
+    interpreter: &str,
+    compression: ScriptCompression,
+    command: &str,
+) {
+    let interpreter_path = std::env::var_os("PATH")
+        .and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(interpreter))
+                .find(|path| path.is_file())
+        })
+        .unwrap_or_else(|| panic!("{}", not_installed(interpreter)));
+    // 展開コマンドを含まない PATH で実行する
+    let empty_dir = std::env::temp_dir().join(format!(
+        "brailler-test-{}-path-{}",
+        std::process::id(),
+        interpreter
+    ));
+    fs::create_dir_all(&empty_dir).unwrap();
+    let options = ScriptOptions {
+        shell,
+        compression,
+        ..Default::default()
+    };
+    let script = generate_script_for_image(IMAGE, &options).unwrap();
+    let path = std::env::temp_dir().join(format!(
+        "brailler-test-{}-missing-{}",
+        std::process::id(),
+        interpreter
+    ));
+    fs::write(&path, script).unwrap();
+    let output = Command::new(interpreter_path)
+        .arg(&path)
+        .env("PATH", &empty_dir)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    fs::remove_dir(&empty_dir).unwrap();
+    assert_eq!(output.status.code(), Some(1), "{}", interpreter);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!("This script needs {}", command)),
+        "{}: {}",
+        interpreter,
+        stderr
+    );
+}
+
+#[test]
+fn script_without_decompressor() {
+    assert_missing_decompressor(ScriptShell::Sh, "dash", ScriptCompression::Xz, "xz");
+    assert_missing_decompressor(ScriptShell::Bash, "bash", ScriptCompression::Zstd, "zstd");
+}
+
+#[test]
+fn script_metadata() {
+    let options = ScriptOptions {
+        shell: ScriptShell::Node,
+        compression: ScriptCompression::Xz,
+        source: Some(ScriptSource {
+            name: "anim.gif".to_string(),
+            sha256: "0123abcd".to_string(),
+            options: "anim.gif --size 4x0\n--scriptify play.js".to_string(),
+        }),
+        ..Default::default()
+    };
+    let script = generate_script_for_video(&moving_dot_frames(), FPS, &options).unwrap();
+    assert!(script.starts_with("#!/usr/bin/env node\n// BRAILLER_METADATA START\n"));
+    let metadata = read_metadata(&script);
+    let value = |key: &str| {
+        metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    assert_eq!(value("version"), Some(env!("CARGO_PKG_VERSION")));
+    assert_eq!(value("source"), Some("anim.gif"));
+    assert_eq!(value("sha256"), Some("0123abcd"));
+    // 改行はコメントが途切れないよう空白になる
+    assert_eq!(
+        value("options"),
+        Some("anim.gif --size 4x0 --scriptify play.js")
+    );
+    assert_eq!(value("size"), Some("4x3"));
+    assert_eq!(value("frames"), Some("6"));
+    assert_eq!(value("fps"), Some("10"));
+    assert_eq!(value("shell"), Some("node"));
+    assert_eq!(value("compression"), Some("xz"));
+
+    let script = generate_script_for_image(IMAGE, &ScriptOptions::default()).unwrap();
+    let metadata = read_metadata(&script);
+    assert!(metadata.contains(&("frames".to_string(), "1".to_string())));
+    assert!(
+        !metadata
+            .iter()
+            .any(|(key, _)| key == "fps" || key == "source")
+    );
+}
+
+#[test]
+fn decode_frames_with_delta() {
+    let frames = moving_dot_frames();
+    let options = ScriptOptions {
+        keyframe_interval: Some(4),
+        ..Default::default()
+    };
+    let decoded = decode_frames(&encode_frames(&frames, FPS, &options).unwrap()).unwrap();
+    assert_eq!(decoded.len(), frames.len());
+    for (decoded, frame) in decoded.iter().zip(&frames) {
+        assert_eq!(decoded.text, frame.text);
+        assert_eq!(decoded.pts, frame.pts);
+    }
+}
+
+#[test]
+fn decode_frames_with_wide_characters() {
+    // 字幕の全角文字の後ろにある変更も、元のセルに戻せる
+    let frames: Vec<TextFrame> = ["⣿⠀\nあいうえ\n", "⣿⠀\nあいかえ\n", "⠀⣿\nあいかお\n"]
+        .iter()
+        .enumerate()
+        .map(|(i, text)| TextFrame {
+            pts: Duration::from_millis(i as u64 * 100),
+            text: text.to_string(),
+        })
+        .collect();
+    let options = ScriptOptions {
+        keyframe_interval: Some(10),
+        ..Default::default()
+    };
+    let decoded = decode_frames(&encode_frames(&frames, FPS, &options).unwrap()).unwrap();
+    let texts: Vec<&str> = decoded.iter().map(|frame| frame.text.as_str()).collect();
+    let expected: Vec<&str> = frames.iter().map(|frame| frame.text.as_str()).collect();
+    assert_eq!(texts, expected);
+}
+
+#[test]
+fn decode_frames_rejects_invalid_delays() {
+    for delay in ["-1", "1e30", "NaN", "abc"] {
+        let payload = format!("{}{}\n\x1b[H⣿\n", FRAME_SEPARATOR, delay);
+        assert!(
+            matches!(decode_frames(&payload), Err(BraillerError::Decode(_))),
+            "{:?} should be rejected",
+            delay
+        );
+    }
+}
+
+#[test]
+fn extract_frames_from_scripts() {
+    let frames = moving_dot_frames();
+    for shell in ScriptShell::value_variants() {
+        for compression in ScriptCompression::value_variants() {
+            let options = ScriptOptions {
+                shell: *shell,
+                compression: *compression,
+                keyframe_interval: Some(4),
+                ..Default::default()
+            };
+            let name = format!("{:?} {:?}", shell, compression);
+            let script = generate_script_for_video(&frames, FPS, &options).unwrap();
+            let texts: Vec<String> = extract_frames(&script)
+                .unwrap()
+                .into_iter()
+                .map(|frame| frame.text)
+                .collect();
+            let expected: Vec<&str> = frames.iter().map(|frame| frame.text.as_str()).collect();
+            assert_eq!(texts, expected, "{}", name);
+
+            let script = generate_script_for_image(IMAGE, &options).unwrap();
+            let extracted = extract_frames(&script).unwrap();
+            assert_eq!(extracted.len(), 1, "{}", name);
+            assert_eq!(extracted[0].text, IMAGE, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use brailler::GrayImage;
+use brailler::args::BinarizeOption;
+use brailler::image_processing::{
+    FrameBinarizer, STABLE_HYSTERESIS, stable_dither, stable_threshold,
+};
+use image::Luma;
+
+fn gradient(width: u32, height: u32) -> GrayImage {
+    GrayImage::from_fn(width, height, |x, y| {
+        Luma([((x + y) * 255 / (width + height - 2)) as u8])
+    })
+}
+
+/// しきい値が中ほどにある画素を探す
+fn middle_pixel() -> (u32, u32, f32) {
+    (0..64)
+        .flat_map(|y| (0..64).map(move |x| (x, y)))
+        .map(|(x, y)| (x, y, stable_threshold(x, y)))
+        .find(|&(_, _, threshold)| (100.0..150.0).contains(&threshold))
+        .unwrap()
+}
+
+/// (x, y) の画素だけが intensity で、それ以外は黒の画像
+fn image_with(x: u32, y: u32, intensity: f32) -> GrayImage {
+    let mut image = GrayImage::new(x + 1, y + 1);
+    image.put_pixel(x, y, Luma([intensity.round() as u8]));
+    image
+}
+
+#[test]
+fn static_input_gives_identical_frames() {
+    let input = gradient(32, 16);
+    let mut binarizer = FrameBinarizer::new(BinarizeOption::Stable);
+    let first = binarizer.binarize(&input);
+    assert_eq!(first, stable_dither(&input, None));
+    for _ in 0..5 {
+        assert_eq!(binarizer.binarize(&input), first);
+    }
+}
+
+#[test]
+fn hysteresis_turns_a_pixel_on_only_after_a_large_change() {
+    let (x, y, threshold) = middle_pixel();
+    let mut binarizer = FrameBinarizer::new(BinarizeOption::Stable);
+    let off = binarizer.binarize(&image_with(x, y, threshold - 10.0));
+    assert_eq!(off.get_pixel(x, y)[0], 0);
+    // しきい値を超えても、差が STABLE_HYSTERESIS 以下なら変わらない
+    let frame = binarizer.binarize(&image_with(x, y, threshold + STABLE_HYSTERESIS - 1.0));
+    assert_eq!(frame.get_pixel(x, y)[0], 0);
+    // 前フレームの結果がない場合は反転する
+    assert_eq!(
+        stable_dither(&image_with(x, y, threshold + STABLE_HYSTERESIS - 1.0), None).get_pixel(x, y)
+            [0],
+        255
+    );
+    let frame = binarizer.binarize(&image_with(x, y, threshold + STABLE_HYSTERESIS + 1.0));
+    assert_eq!(frame.get_pixel(x, y)[0], 255);
+}
+
+#[test]
+fn hysteresis_turns_a_pixel_off_only_after_a_large_change() {
+    let (x, y, threshold) = middle_pixel();
+    let on = image_with(x, y, threshold + 10.0);
+    assert_eq!(stable_dither(&on, None).get_pixel(x, y)[0], 255);
+    let previous = stable_dither(&on, None);
+    let frame = stable_dither(
+        &image_with(x, y, threshold - STABLE_HYSTERESIS + 1.0),
+        Some(&previous),
+    );
+    assert_eq!(frame.get_pixel(x, y)[0], 255);
+    let frame = stable_dither(
+        &image_with(x, y, threshold - STABLE_HYSTERESIS - 1.0),
+        Some(&previous),
+    );
+    assert_eq!(frame.get_pixel(x, y)[0], 0);
+}
+
+#[test]
+fn size_change_resets_the_previous_frame() {
+    let mut binarizer = FrameBinarizer::new(BinarizeOption::Stable);
+    binarizer.binarize(&gradient(8, 8));
+    let input = gradient(16, 8);
+    assert_eq!(binarizer.binarize(&input), stable_dither(&input, None));
+}
@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use brailler::subtitles::{Cue, Subtitles};
+use brailler::timeline::Timeline;
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+const SRT: &str = "\u{feff}1\r\n\
+00:00:01,000 --> 00:00:03,500\r\n\
+<i>Hello</i>, {\\an8}world\r\n\
+\r\n\
+2\r\n\
+00:00:03,000 --> 00:00:05,000\r\n\
+Second line one\r\n\
+Second line two\r\n";
+
+const VTT: &str = "WEBVTT
+
+NOTE this block has no timing
+
+intro
+00:01.000 --> 00:02.000 align:start position:10%
+<v Speaker>Fish &amp; chips &lt;3</v>
+
+00:00:02.500 --> 00:00:04.000
+<c.yellow>Hi</c>
+";
+
+#[test]
+fn parse_srt() {
+    let subtitles = Subtitles::parse(SRT).unwrap();
+    assert_eq!(
+        subtitles.cues(),
+        [
+            Cue {
+                start: ms(1000),
+                end: ms(3500),
+                text: "Hello, world".to_string(),
+            },
+            Cue {
+                start: ms(3000),
+                end: ms(5000),
+                text: "Second line one\nSecond line two".to_string(),
+            },
+        ]
+    );
+    assert_eq!(subtitles.lines(), 2);
+}
+
+#[test]
+fn parse_vtt() {
+    let subtitles = Subtitles::parse(VTT).unwrap();
+    assert_eq!(
+        subtitles.cues(),
+        [
+            Cue {
+                start: ms(1000),
+                end: ms(2000),
+                text: "Fish & chips <3".to_string(),
+            },
+            Cue {
+                start: ms(2500),
+                end: ms(4000),
+                text: "Hi".to_string(),
+            },
+        ]
+    );
+    assert_eq!(subtitles.lines(), 1);
+}
+
+#[test]
+fn parse_invalid_timing() {
+    assert!(Subtitles::parse("1\n00:00:01,000 --> later\nText\n").is_err());
+}
+
+#[test]
+fn text_at_overlapping_cues() {
+    let subtitles = Subtitles::parse(SRT).unwrap();
+    assert_eq!(subtitles.text_at(ms(500)), None);
+    assert_eq!(
+        subtitles.text_at(ms(1000)),
+        Some("Hello, world".to_string())
+    );
+    assert_eq!(
+        subtitles.text_at(ms(3200)),
+        Some("Hello, world\nSecond line one\nSecond line two".to_string())
+    );
+    // 終了時刻ちょうどには表示しない
+    assert_eq!(
+        subtitles.text_at(ms(3500)),
+        Some("Second line one\nSecond line two".to_string())
+    );
+    assert_eq!(subtitles.text_at(ms(5000)), None);
+}
+
+#[test]
+fn retime_to_output() {
+    let timeline = Timeline {
+        start: ms(2000),
+        end: None,
+        speed: 2.0,
+        sample_interval: None,
+    };
+    let subtitles = Subtitles::parse(SRT).unwrap().retime(&timeline);
+    assert_eq!(
+        subtitles
+            .cues()
+            .iter()
+            .map(|cue| (cue.start, cue.end))
+            .collect::<Vec<_>>(),
+        [(ms(0), ms(750)), (ms(500), ms(1500))]
+    );
+
+    // 切り出し開始前に終わる項目は取り除かれる
+    let timeline = Timeline {
+        start: ms(4000),
+        ..timeline
+    };
+    let subtitles = Subtitles::parse(SRT).unwrap().retime(&timeline);
+    assert_eq!(subtitles.cues().len(), 1);
+}
+
+#[test]
+fn render_area_centers_and_pads() {
+    let subtitles = Subtitles::parse(SRT).unwrap();
+    // 字幕のない時点も lines() 行の空白になる
+    assert_eq!(subtitles.render_area(ms(0), 4), "    \n    \n");
+    // 1行の字幕は下に寄せる
+    assert_eq!(
+        subtitles.render_area(ms(1000), 16),
+        format!("{}\n  Hello, world  \n", " ".repeat(16))
+    );
+    // 幅を超える部分は切り捨てる
+    assert_eq!(
+        subtitles.render_area(ms(1000), 5),
+        format!("{}\nHello\n", " ".repeat(5))
+    );
+}
+
+#[test]
+fn render_area_uses_display_width() {
+    let subtitles = Subtitles::parse("1\n00:00:00,000 --> 00:00:01,000\n字幕\n").unwrap();
+    // 全角文字は2桁として中央揃えする
+    assert_eq!(subtitles.render_area(ms(0), 8), "  字幕  \n");
+    // 全角文字の途中で切れる場合は、その文字を含めず空白で埋める
+    assert_eq!(subtitles.render_area(ms(0), 3), "字 \n");
+}
@@ -0,0 +1,150 @@
+use brailler::terminal::{
+    DiffRenderer, Key, display_width, parse_keys, same_shape, write_changed_runs,
+};
+
+fn cells(frame: &str) -> Vec<Vec<char>> {
+    frame.lines().map(|line| line.chars().collect()).collect()
+}
+
+fn changed_runs(prev: &str, next: &str) -> String {
+    let mut output = String::new();
+    let prev: Vec<char> = prev.chars().collect();
+    let next: Vec<char> = next.chars().collect();
+    write_changed_runs(&mut output, 0, &prev, &next);
+    output
+}
+
+#[test]
+fn first_frame_is_drawn_in_full() {
+    let mut renderer = DiffRenderer::new(false);
+    assert_eq!(
+        renderer.render("⠁⠂\n⠄⡀\n"),
+        "\x1B[2J\x1B[1;1H⠁⠂\n⠄⡀\n\x1B[3;1H"
+    );
+}
+
+#[test]
+fn synchronized_output_wraps_the_frame() {
+    let mut renderer = DiffRenderer::new(true);
+    assert_eq!(
+        renderer.render("⠁\n"),
+        "\x1B[?2026h\x1B[2J\x1B[1;1H⠁\n\x1B[2;1H\x1B[?2026l"
+    );
+}
+
+#[test]
+fn unchanged_frame_produces_no_output() {
+    for synchronized in [false, true] {
+        let mut renderer = DiffRenderer::new(synchronized);
+        renderer.render("⠁⠂\n⠄⡀\n");
+        assert_eq!(renderer.render("⠁⠂\n⠄⡀\n"), "");
+    }
+}
+
+#[test]
+fn changed_cells_are_rewritten() {
+    let mut renderer = DiffRenderer::new(false);
+    renderer.render("⠁⠂⠄⡀\n⠁⠂⠄⡀\n");
+    // 2行目の3桁目のみ変更
+    assert_eq!(renderer.render("⠁⠂⠄⡀\n⠁⠂⣿⡀\n"), "\x1B[2;3H⣿\x1B[3;1H");
+}
+
+#[test]
+fn invalidate_forces_full_redraw() {
+    let mut renderer = DiffRenderer::new(false);
+    renderer.render("⠁\n");
+    renderer.invalidate();
+    assert_eq!(renderer.render("⠁\n"), "\x1B[2J\x1B[1;1H⠁\n\x1B[2;1H");
+}
+
+#[test]
+fn shape_change_forces_full_redraw() {
+    let mut renderer = DiffRenderer::new(false);
+    renderer.render("⠁⠂\n⠄⡀\n");
+    // 行数が変わった場合
+    assert_eq!(
+        renderer.render("⠁⠂\n⠄⡀\n⠁⠂\n"),
+        "\x1B[2J\x1B[1;1H⠁⠂\n⠄⡀\n⠁⠂\n\x1B[4;1H"
+    );
+    // 桁数が変わった場合
+    assert_eq!(
+        renderer.render("⠁⠂⠄\n⠄⡀⠁\n⠁⠂⠄\n"),
+        "\x1B[2J\x1B[1;1H⠁⠂⠄\n⠄⡀⠁\n⠁⠂⠄\n\x1B[4;1H"
+    );
+}
+
+#[test]
+fn same_shape_compares_rows_and_columns() {
+    assert!(same_shape(&cells("ab\ncd"), &cells("xy\nzw")));
+    assert!(same_shape(&[], &[]));
+    assert!(!same_shape(&[], &cells("a")));
+    assert!(!same_shape(&cells("ab\ncd"), &cells("ab")));
+    assert!(!same_shape(&cells("ab\ncd"), &cells("ab\ncde")));
+}
+
+#[test]
+fn write_changed_runs_single_run() {
+    assert_eq!(changed_runs("abcdef", "abcdef"), "");
+    assert_eq!(changed_runs("abcdef", "aXYdef"), "\x1B[1;2HXY");
+    // y は 1 始まりの行になる
+    let mut output = String::new();
+    write_changed_runs(&mut output, 4, &['a'], &['b']);
+    assert_eq!(output, "\x1B[5;1Hb");
+}
+
+#[test]
+fn write_changed_runs_merges_small_gaps() {
+    // 未変更セルが MAX_GAP (2) 個以下なら1つの区間にまとめる
+    assert_eq!(changed_runs("abcdefgh", "XbcYefgh"), "\x1B[1;1HXbcY");
+    assert_eq!(changed_runs("abcdefgh", "XbYdefgh"), "\x1B[1;1HXbY");
+    // 3個以上離れていれば別の区間にする
+    assert_eq!(changed_runs("abcdefgh", "XbcdYfgh"), "\x1B[1;1HX\x1B[1;5HY");
+    // まとめた区間の後の未変更セルは書き直さない
+    assert_eq!(
+        changed_runs("abcdefgh", "XbYdefgZ"),
+        "\x1B[1;1HXbY\x1B[1;8HZ"
+    );
+}
+
+#[test]
+fn wide_characters_are_positioned_by_display_width() {
+    assert_eq!(display_width("⣿あいa"), 6);
+    // 「う」は表示上の 5 桁目から始まる
+    assert_eq!(changed_runs("あいうえ", "あいかえ"), "\x1B[1;5Hか");
+    assert_eq!(changed_runs("⣿あいうえ", "⣿あいうお"), "\x1B[1;8Hお");
+
+    let mut renderer = DiffRenderer::new(false);
+    renderer.render("⣿⣿\nあいうえ\n");
+    assert_eq!(renderer.render("⣿⣿\nあいかえ\n"), "\x1B[2;5Hか\x1B[3;1H");
+}
+
+#[test]
+fn same_shape_compares_cell_widths() {
+    assert!(same_shape(&cells("あい"), &cells("かえ")));
+    // セルの数が同じでも、全角文字の位置が変わる場合は形が異なる
+    assert!(!same_shape(&cells("あa"), &cells("aあ")));
+    assert!(!same_shape(&cells("あ"), &cells("a")));
+}
+
+#[test]
+fn parse_keys_arrows() {
+    assert_eq!(parse_keys(b"\x1B[C\x1B[D"), [Key::Right, Key::Left]);
+    // 上下の矢印キーは無視し、続くキーは読み取る
+    assert_eq!(parse_keys(b"\x1B[A\x1B[Bq"), [Key::Char('q')]);
+    // 途中で切れたエスケープシーケンス
+    assert_eq!(parse_keys(b"\x1B["), []);
+}
+
+#[test]
+fn parse_keys_controls() {
+    assert_eq!(
+        parse_keys(b" q\x03"),
+        [Key::Space, Key::Char('q'), Key::Interrupt]
+    );
+    assert_eq!(
+        parse_keys(b"+=-_l"),
+        [Key::Plus, Key::Plus, Key::Minus, Key::Minus, Key::Char('l')]
+    );
+    // 改行などの制御文字は無視する
+    assert_eq!(parse_keys(b"\n\r\t"), []);
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use brailler::GrayImage;
+use brailler::error::Result;
+use brailler::player::Frame;
+use brailler::thumbnails::{BINS, detect_scenes, difference, histogram};
+use image::Luma;
+
+fn frame(value: u8, millis: u64) -> Frame {
+    Frame {
+        image: GrayImage::from_pixel(4, 4, Luma([value])),
+        pts: Duration::from_millis(millis),
+    }
+}
+
+/// value の明るさのフレームを 100ms 間隔で並べる
+fn frames(values: &[u8]) -> impl Iterator<Item = Result<Frame>> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| Ok(frame(value, i as u64 * 100)))
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+#[test]
+fn histogram_counts_pixels_per_bin() {
+    let mut image = GrayImage::from_pixel(4, 2, Luma([0]));
+    image.put_pixel(0, 0, Luma([255]));
+    image.put_pixel(1, 0, Luma([(256 / BINS) as u8]));
+    let hist = histogram(&Frame {
+        image,
+        pts: Duration::ZERO,
+    });
+    assert_eq!(hist[0], 6);
+    assert_eq!(hist[1], 1);
+    assert_eq!(hist[BINS - 1], 1);
+    assert_eq!(hist.iter().sum::<u32>(), 8);
+}
+
+#[test]
+fn difference_range() {
+    let black = histogram(&frame(0, 0));
+    let white = histogram(&frame(255, 0));
+    assert_eq!(difference(&black, &black), 0.0);
+    assert_eq!(difference(&black, &white), 1.0);
+
+    // 半分のピクセルが変わった場合は 0.5
+    let mut image = GrayImage::from_pixel(4, 4, Luma([0]));
+    for y in 0..2 {
+        for x in 0..4 {
+            image.put_pixel(x, y, Luma([255]));
+        }
+    }
+    let half = histogram(&Frame {
+        image,
+        pts: Duration::ZERO,
+    });
+    assert_eq!(difference(&black, &half), 0.5);
+}
+
+#[test]
+fn detect_scenes_splits_on_changes() {
+    let scenes = detect_scenes(frames(&[0, 0, 0, 255, 255, 0]), 0.3, Duration::ZERO).unwrap();
+    let starts: Vec<Duration> = scenes.iter().map(|scene| scene.start).collect();
+    assert_eq!(starts, [0, 300, 500].map(Duration::from_millis).to_vec());
+    let counts: Vec<usize> = scenes.iter().map(|scene| scene.frame_count).collect();
+    assert_eq!(counts, [3, 2, 1]);
+}
+
+#[test]
+fn detect_scenes_merges_short_scenes() {
+    // 2つ目の切り替わりは前のシーンの開始から 200ms 未満なので区切らない
+    let scenes =
+        detect_scenes(frames(&[0, 0, 255, 0, 0]), 0.3, Duration::from_millis(200)).unwrap();
+    let starts: Vec<Duration> = scenes.iter().map(|scene| scene.start).collect();
+    assert_eq!(starts, [0, 200].map(Duration::from_millis).to_vec());
+    assert_eq!(scenes[1].frame_count, 3);
+}
+
+#[test]
+fn detect_scenes_ignores_small_differences() {
+    let scenes = detect_scenes(frames(&[0, 4, 0, 4]), 0.3, Duration::ZERO).unwrap();
+    assert_eq!(scenes.len(), 1);
+    assert_eq!(scenes[0].frame_count, 4);
+}
+
+#[test]
+fn detect_scenes_with_decreasing_pts() {
+    // pts が戻っても (シーク後の動画など) パニックしない
+    let input = [frame(0, 500), frame(255, 100)].into_iter().map(Ok);
+    let scenes = detect_scenes(input, 0.3, Duration::from_millis(100)).unwrap();
+    assert_eq!(scenes.len(), 1);
+}
+
+#[test]
+fn scene_frame_is_near_the_middle() {
+    for count in 1..200 {
+        let input = (0..count).map(|i| Ok(frame(0, i as u64)));
+        let scenes = detect_scenes(input, 0.3, Duration::ZERO).unwrap();
+        assert_eq!(scenes.len(), 1);
+        assert_eq!(scenes[0].frame_count, count);
+        let index = scenes[0].frame().pts.as_millis() as usize;
+        assert!(
+            index.abs_diff(count / 2) <= count / 4,
+            "frame {} of {} is too far from the middle",
+            index,
+            count
+        );
+    }
+}
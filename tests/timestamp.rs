@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use brailler::args::Args;
+use brailler::error::BraillerError;
+use brailler::timeline::{Timeline, frame_interval};
+use brailler::timestamp::Timestamp;
+use clap::Parser;
+
+fn parse(s: &str) -> Result<Duration, String> {
+    s.parse::<Timestamp>().map(|timestamp| timestamp.0)
+}
+
+#[test]
+fn parse_seconds() {
+    assert_eq!(parse("90"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse("2.5"), Ok(Duration::from_millis(2500)));
+    assert_eq!(parse(" 0 "), Ok(Duration::ZERO));
+}
+
+#[test]
+fn parse_minutes_and_hours() {
+    assert_eq!(parse("1:30"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse("1:02:03.5"), Ok(Duration::from_millis(3_723_500)));
+    assert_eq!(parse("00:00:01"), Ok(Duration::from_secs(1)));
+}
+
+#[test]
+fn parse_rejects_invalid_forms() {
+    for s in [
+        "", "-1", "1:-30", "-1:30", "NaN", "inf", "1:2:3:4", "1.5:30", "1:abc", "1e300", "1:1e300",
+    ] {
+        assert!(parse(s).is_err(), "{:?} should be rejected", s);
+    }
+}
+
+#[test]
+fn display_timestamp() {
+    assert_eq!(
+        Timestamp(Duration::from_millis(3_723_500)).to_string(),
+        "01:02:03.500"
+    );
+    assert_eq!(Timestamp(Duration::ZERO).to_string(), "00:00:00.000");
+}
+
+fn parse_timeline(options: &[&str]) -> brailler::error::Result<Timeline> {
+    let args = Args::try_parse_from(["brailler", "input.mp4"].iter().chain(options)).unwrap();
+    Timeline::new(&args)
+}
+
+#[test]
+fn timeline_sample_interval() {
+    let timeline = parse_timeline(&["--fps", "4"]).unwrap();
+    assert_eq!(timeline.sample_interval, Some(Duration::from_millis(250)));
+    assert_eq!(timeline.output_fps(30.0).unwrap(), 4.0);
+
+    let timeline = parse_timeline(&[]).unwrap();
+    assert_eq!(timeline.sample_interval, None);
+    assert_eq!(timeline.output_fps(30.0).unwrap(), 30.0);
+}
+
+#[test]
+fn frame_interval_rejects_invalid_fps() {
+    assert_eq!(frame_interval(4.0).unwrap(), Duration::from_millis(250));
+    // 0/0 のフレームレートは NaN になる
+    for fps in [0.0, -1.0, f32::NAN, f32::INFINITY, 1e-30] {
+        assert!(
+            matches!(frame_interval(fps), Err(BraillerError::Decode(_))),
+            "fps {} should be rejected",
+            fps
+        );
+    }
+    let timeline = parse_timeline(&[]).unwrap();
+    assert!(timeline.output_fps(f32::NAN).is_err());
+    assert!(timeline.output_fps(0.0).is_err());
+}
+
+#[test]
+fn timeline_conversions_saturate() {
+    let timeline = Timeline {
+        start: Duration::from_secs(1),
+        end: None,
+        speed: 0.01,
+        sample_interval: None,
+    };
+    assert_eq!(timeline.to_output(Duration::MAX), Duration::MAX);
+    assert_eq!(timeline.to_output(Duration::ZERO), Duration::ZERO);
+    assert_eq!(
+        timeline.to_output(Duration::from_secs(2)),
+        Duration::from_secs(100)
+    );
+    let timeline = Timeline {
+        speed: 100.0,
+        ..timeline
+    };
+    assert_eq!(timeline.to_source(Duration::MAX), Duration::MAX);
+    assert_eq!(
+        timeline.to_source(Duration::from_secs(1)),
+        Duration::from_secs(101)
+    );
+}
+
+#[test]
+fn timeline_rejects_out_of_range_values() {
+    for options in [
+        &["--fps", "1e-30"][..],
+        &["--fps=-1"],
+        &["--speed", "0"],
+        &["--speed", "1e-30"],
+        &["--speed", "1e30"],
+        &["--speed", "NaN"],
+        &["--start", "10", "--end", "5"],
+        &["--start", "1e19", "--duration", "1e19"],
+    ] {
+        assert!(
+            matches!(
+                parse_timeline(options),
+                Err(BraillerError::InvalidArgument(_))
+            ),
+            "{:?} should be rejected",
+            options
+        );
+    }
+}